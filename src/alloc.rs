@@ -0,0 +1,85 @@
+//! Counting global allocator, enabled with the `alloc-stats` feature. Keeps
+//! track of the current and peak heap usage and of the number of allocations
+//! (reallocations included).
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingAlloc;
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn grow(size: usize) {
+    let current = CURRENT.fetch_add(size, Relaxed) + size;
+    PEAK.fetch_max(current, Relaxed);
+    ALLOCATIONS.fetch_add(1, Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            CURRENT.fetch_sub(layout.size(), Relaxed);
+            grow(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Heap usage at some point, see `snapshot`.
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    current: usize,
+    allocations: usize,
+}
+
+/// Heap usage between a `Snapshot` and now.
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    /// Peak heap usage in bytes, on top of what was in use at the snapshot.
+    pub peak: usize,
+    pub allocations: usize,
+}
+
+/// Takes a snapshot of the heap usage and restarts tracking the peak from it.
+pub fn snapshot() -> Snapshot {
+    let current = CURRENT.load(Relaxed);
+    PEAK.store(current, Relaxed);
+    Snapshot {
+        current,
+        allocations: ALLOCATIONS.load(Relaxed),
+    }
+}
+
+pub fn since(snapshot: &Snapshot) -> Stats {
+    Stats {
+        peak: PEAK.load(Relaxed).saturating_sub(snapshot.current),
+        allocations: ALLOCATIONS.load(Relaxed) - snapshot.allocations,
+    }
+}
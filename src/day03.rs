@@ -1,52 +1,363 @@
 use aoc::*;
+use std::fmt;
+use std::fmt::Write;
 
-fn get_priority(item: char) -> i32
-{
-    let item_ascii: i32 = item as i32;
-    if item_ascii >= 97 {
-        return item_ascii - 96
+const COMPARTMENTS: usize = 2;
+const GROUP_SIZE: usize = 3;
+
+#[derive(Debug, PartialEq)]
+enum RucksackError {
+    InvalidItem { line: usize, item: char },
+    IncompleteGroup { size: usize, rucksacks: usize },
+    NoCompartments,
+    EmptyGroups,
+}
+
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RucksackError::InvalidItem { line, item } => {
+                write!(f, "Invalid item `{item}` on line {line}, items are letters")
+            }
+            RucksackError::IncompleteGroup { size, rucksacks } => write!(
+                f,
+                "{rucksacks} rucksacks can't be split in groups of {size}, \
+                 the last group has only {}",
+                rucksacks % size
+            ),
+            RucksackError::NoCompartments => write!(f, "Rucksacks need at least one compartment"),
+            RucksackError::EmptyGroups => write!(f, "Groups need at least one rucksack"),
+        }
+    }
+}
+
+fn get_priority(item: char) -> Option<u32> {
+    match item {
+        'a'..='z' => Some(item as u32 - 'a' as u32 + 1),
+        'A'..='Z' => Some(item as u32 - 'A' as u32 + 27),
+        _ => None,
     }
-    return item_ascii - 64 + 26
 }
 
-fn part1(input: &Vec<String>) 
-{
-    let mut priorities: Vec<i32> = Vec::new();
-    for line in input.iter() {
-        let (left, right) = line.split_at(line.len()/2);
+/// Set of items, the item of priority `p` is bit `p - 1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Items(u64);
+
+impl Items {
+    const ALL: Items = Items((1 << 52) - 1);
 
-        for i in left.chars() {
-            if right.contains(i) {
-                priorities.push(get_priority(i));
-                break;
+    fn parse(items: &str, line: usize) -> Result<Self, RucksackError> {
+        let mut set = 0;
+        for item in items.chars() {
+            match get_priority(item) {
+                Some(priority) => set |= 1 << (priority - 1),
+                None => return Err(RucksackError::InvalidItem { line, item }),
             }
         }
+        Ok(Items(set))
     }
 
-    // println!("{:?}", priorities);
-    let sum: i32 = priorities.iter().sum();
-    println!("{0}", sum);
+    fn intersection(self, other: Items) -> Items {
+        Items(self.0 & other.0)
+    }
+
+    fn union(self, other: Items) -> Items {
+        Items(self.0 | other.0)
+    }
+
+    /// Items in order of priority
+    fn iter(self) -> impl Iterator<Item = char> {
+        ('a'..='z')
+            .chain('A'..='Z')
+            .enumerate()
+            .filter(move |(i, _)| self.0 & (1 << i) != 0)
+            .map(|(_, item)| item)
+    }
+
+    fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    fn priority(self) -> u32 {
+        self.iter().filter_map(get_priority).sum()
+    }
 }
 
-fn part2(input: &Vec<String>) 
-{
-    let mut priorities: Vec<i32> = Vec::new();
-    for group in (0..input.len()).step_by(3) {
-        // if group + 2 > input.len() { break; }
+#[derive(Debug)]
+struct Rucksack {
+    compartments: Vec<Items>,
+    // The compartments don't all have the same size
+    uneven: bool,
+}
 
-        let first_elf = &input[group];
+impl Rucksack {
+    /// Splits the items of `line` in `compartments` parts of the same size. If
+    /// that's not possible the last compartment takes the extra items.
+    fn parse(items: &str, line: usize, compartments: usize) -> Result<Self, RucksackError> {
+        // Items are split by byte, which only works with ASCII
+        if let Some(item) = items.chars().find(|c| !c.is_ascii()) {
+            return Err(RucksackError::InvalidItem { line, item });
+        }
+        let size = items.len() / compartments;
+        let mut ret = Rucksack {
+            compartments: Vec::with_capacity(compartments),
+            uneven: !items.len().is_multiple_of(compartments),
+        };
+        for i in 0..compartments {
+            let end = if i + 1 == compartments {
+                items.len()
+            } else {
+                (i + 1) * size
+            };
+            ret.compartments
+                .push(Items::parse(&items[i * size..end], line)?);
+        }
+        Ok(ret)
+    }
 
-        for i in first_elf.chars() {
-            if input[group+1].contains(i) && input[group+2].contains(i) {
-                priorities.push(get_priority(i));
-                break;
-            }
+    fn items(&self) -> Items {
+        self.compartments
+            .iter()
+            .fold(Items(0), |acc, &c| acc.union(c))
+    }
+
+    /// Items in every compartment
+    fn shared(&self) -> Items {
+        self.compartments
+            .iter()
+            .fold(Items::ALL, |acc, &c| acc.intersection(c))
+    }
+}
+
+fn parse_rucksacks(input: &[String], compartments: usize) -> Result<Vec<Rucksack>, RucksackError> {
+    if compartments == 0 {
+        return Err(RucksackError::NoCompartments);
+    }
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, ln)| Rucksack::parse(ln, i + 1, compartments))
+        .collect()
+}
+
+/// Consecutive groups of `size` rucksacks
+fn group_by(rucksacks: &[Rucksack], size: usize) -> Result<Vec<&[Rucksack]>, RucksackError> {
+    if size == 0 {
+        return Err(RucksackError::EmptyGroups);
+    }
+    if !rucksacks.len().is_multiple_of(size) {
+        return Err(RucksackError::IncompleteGroup {
+            size,
+            rucksacks: rucksacks.len(),
+        });
+    }
+    Ok(rucksacks.chunks(size).collect())
+}
+
+/// Items carried by every elf of the group
+fn badges(group: &[Rucksack]) -> Items {
+    group
+        .iter()
+        .fold(Items::ALL, |acc, r| acc.intersection(r.items()))
+}
+
+fn shared_priorities(input: &[String], compartments: usize) -> Result<u32, RucksackError> {
+    let rucksacks = parse_rucksacks(input, compartments)?;
+    Ok(rucksacks.iter().map(|r| r.shared().priority()).sum())
+}
+
+fn badge_priorities(input: &[String], group_size: usize) -> Result<u32, RucksackError> {
+    let rucksacks = parse_rucksacks(input, COMPARTMENTS)?;
+    let groups = group_by(&rucksacks, group_size)?;
+    Ok(groups.iter().map(|&g| badges(g).priority()).sum())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Anomaly {
+    NoCommonItem,
+    MultipleCommonItems,
+    UnevenCompartments,
+    IncompleteGroup,
+}
+
+impl Anomaly {
+    fn as_str(self) -> &'static str {
+        match self {
+            Anomaly::NoCommonItem => "no common item",
+            Anomaly::MultipleCommonItems => "multiple common items",
+            Anomaly::UnevenCompartments => "uneven compartments",
+            Anomaly::IncompleteGroup => "incomplete group",
         }
     }
 
-    // println!("{:?}", priorities);
-    let sum: i32 = priorities.iter().sum();
-    println!("{0}", sum);
+    fn of_common(items: Items) -> Option<Anomaly> {
+        match items.len() {
+            0 => Some(Anomaly::NoCommonItem),
+            1 => None,
+            _ => Some(Anomaly::MultipleCommonItems),
+        }
+    }
+}
+
+/// Common items of a rucksack or group, and anything suspicious about it
+#[derive(Debug)]
+struct ReportEntry {
+    // First and last line (1-based) of the rucksack or group
+    lines: (usize, usize),
+    common: Items,
+    anomalies: Vec<Anomaly>,
+}
+
+impl ReportEntry {
+    fn describe(&self) -> String {
+        let (first, last) = self.lines;
+        let mut ret = if first == last {
+            format!("line {first}: ")
+        } else {
+            format!("lines {first}-{last}: ")
+        };
+        if self.common.len() == 0 {
+            ret.push('-');
+        } else {
+            write!(
+                ret,
+                "{} ({})",
+                self.common.iter().collect::<String>(),
+                self.common.priority()
+            )
+            .unwrap();
+        }
+        if !self.anomalies.is_empty() {
+            let anomalies: Vec<_> = self.anomalies.iter().map(|a| a.as_str()).collect();
+            write!(ret, " [{}]", anomalies.join(", ")).unwrap();
+        }
+        ret
+    }
+
+    fn to_json(&self) -> String {
+        let anomalies: Vec<_> = self
+            .anomalies
+            .iter()
+            .map(|a| format!("\"{}\"", a.as_str()))
+            .collect();
+        format!(
+            "{{\"lines\": [{}, {}], \"items\": \"{}\", \"priority\": {}, \"anomalies\": [{}]}}",
+            self.lines.0,
+            self.lines.1,
+            self.common.iter().collect::<String>(),
+            self.common.priority(),
+            anomalies.join(", ")
+        )
+    }
+}
+
+/// Audit of every rucksack and group. Unlike the solutions it also goes
+/// through incomplete groups, flagging them.
+#[derive(Debug)]
+struct Report {
+    rucksacks: Vec<ReportEntry>,
+    groups: Vec<ReportEntry>,
+}
+
+impl Report {
+    fn new(
+        input: &[String],
+        compartments: usize,
+        group_size: usize,
+    ) -> Result<Self, RucksackError> {
+        if group_size == 0 {
+            return Err(RucksackError::EmptyGroups);
+        }
+        let rucksacks = parse_rucksacks(input, compartments)?;
+
+        let rucksack_entries = rucksacks
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let common = r.shared();
+                let mut anomalies: Vec<Anomaly> = Anomaly::of_common(common).into_iter().collect();
+                if r.uneven {
+                    anomalies.push(Anomaly::UnevenCompartments);
+                }
+                ReportEntry {
+                    lines: (i + 1, i + 1),
+                    common,
+                    anomalies,
+                }
+            })
+            .collect();
+
+        let group_entries = rucksacks
+            .chunks(group_size)
+            .enumerate()
+            .map(|(i, group)| {
+                let common = badges(group);
+                let mut anomalies: Vec<Anomaly> = Anomaly::of_common(common).into_iter().collect();
+                if group.len() < group_size {
+                    anomalies.push(Anomaly::IncompleteGroup);
+                }
+                ReportEntry {
+                    lines: (i * group_size + 1, i * group_size + group.len()),
+                    common,
+                    anomalies,
+                }
+            })
+            .collect();
+
+        Ok(Report {
+            rucksacks: rucksack_entries,
+            groups: group_entries,
+        })
+    }
+
+    fn to_json(&self) -> String {
+        let entries = |entries: &[ReportEntry]| {
+            entries
+                .iter()
+                .map(|e| format!("    {}", e.to_json()))
+                .collect::<Vec<_>>()
+                .join(",\n")
+        };
+        format!(
+            "{{\n  \"rucksacks\": [\n{}\n  ],\n  \"groups\": [\n{}\n  ]\n}}",
+            entries(&self.rucksacks),
+            entries(&self.groups)
+        )
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Rucksacks:")?;
+        for entry in self.rucksacks.iter() {
+            writeln!(f, "  {}", entry.describe())?;
+        }
+        writeln!(f, "Groups:")?;
+        for entry in self.groups.iter() {
+            writeln!(f, "  {}", entry.describe())?;
+        }
+
+        let anomalies = self
+            .rucksacks
+            .iter()
+            .chain(self.groups.iter())
+            .filter(|e| !e.anomalies.is_empty());
+        writeln!(f, "{} anomalies", anomalies.count())
+    }
+}
+
+fn part1(input: &[String]) {
+    match shared_priorities(input, COMPARTMENTS) {
+        Ok(sum) => println!("{0}", sum),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+fn part2(input: &[String]) {
+    match badge_priorities(input, GROUP_SIZE) {
+        Ok(sum) => println!("{0}", sum),
+        Err(e) => eprintln!("{e}"),
+    }
 }
 
 fn main() {
@@ -56,14 +367,108 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: Vec<String> = match read_inputs(&file_path) {
         Ok(input) => input,
         Err(e) => panic!("Error parsing the inputs: {:?}", e)
     };
-    println!("PART 1:");
-    part1(&input);
 
-    println!("PART 1:");
-    part2(&input);
+    if has_flag("--report") {
+        match Report::new(&input, COMPARTMENTS, GROUP_SIZE) {
+            Ok(report) if has_flag("--json") => println!("{}", report.to_json()),
+            Ok(report) => print!("{report}"),
+            Err(e) => eprintln!("{e}"),
+        }
+        return;
+    }
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(input: &[&str]) -> Vec<String> {
+        input.iter().map(|ln| ln.to_string()).collect()
+    }
+
+    #[test]
+    fn items_bitset() {
+        let items = Items::parse("abzAZZ", 1).unwrap();
+        assert_eq!(items.iter().collect::<String>(), "abzAZ");
+        assert_eq!(items.priority(), 1 + 2 + 26 + 27 + 52);
+
+        let shared = Rucksack::parse("abcAaBCb", 1, 2).unwrap().shared();
+        assert_eq!(shared.iter().collect::<String>(), "ab");
+        let shared = Rucksack::parse("xaybzc", 1, 3).unwrap().shared();
+        assert_eq!(shared, Items(0));
+    }
+
+    #[test]
+    fn rucksack_errors() {
+        assert_eq!(
+            shared_priorities(&lines(&["abab", "ab1ab1"]), 2),
+            Err(RucksackError::InvalidItem { line: 2, item: '1' })
+        );
+        assert_eq!(
+            shared_priorities(&lines(&["aéb"]), 2),
+            Err(RucksackError::InvalidItem { line: 1, item: 'é' })
+        );
+        assert_eq!(
+            badge_priorities(&lines(&["aa", "aa", "aa", "bb"]), 3),
+            Err(RucksackError::IncompleteGroup {
+                size: 3,
+                rucksacks: 4
+            })
+        );
+        assert_eq!(
+            badge_priorities(&lines(&["aa", "aa", "aa", "bb"]), 2),
+            Ok(1)
+        );
+        assert_eq!(
+            shared_priorities(&lines(&["abab"]), 0),
+            Err(RucksackError::NoCompartments)
+        );
+        assert_eq!(
+            badge_priorities(&lines(&["aa", "aa"]), 0),
+            Err(RucksackError::EmptyGroups)
+        );
+        assert_eq!(
+            Report::new(&lines(&["aa"]), 0, 3).unwrap_err(),
+            RucksackError::NoCompartments
+        );
+        assert_eq!(
+            Report::new(&lines(&["aa"]), 2, 0).unwrap_err(),
+            RucksackError::EmptyGroups
+        );
+    }
+
+    #[test]
+    fn report_anomalies() {
+        let input = lines(&["abcdbe", "abab", "xyz", "cdcdc"]);
+        let report = Report::new(&input, 2, 3).unwrap();
+
+        let anomalies: Vec<_> = report
+            .rucksacks
+            .iter()
+            .map(|e| e.anomalies.clone())
+            .collect();
+        assert_eq!(
+            anomalies,
+            [
+                vec![],
+                vec![Anomaly::MultipleCommonItems],
+                vec![Anomaly::NoCommonItem, Anomaly::UnevenCompartments],
+                vec![Anomaly::MultipleCommonItems, Anomaly::UnevenCompartments],
+            ]
+        );
+        assert_eq!(report.groups[0].describe(), "lines 1-3: - [no common item]");
+        assert_eq!(
+            report.groups[1].to_json(),
+            "{\"lines\": [4, 4], \"items\": \"cd\", \"priority\": 7, \
+             \"anomalies\": [\"multiple common items\", \"incomplete group\"]}"
+        );
+    }
 }
@@ -1,75 +1,293 @@
 use aoc::*;
+use std::fmt;
+use std::num::NonZeroUsize;
 
-struct Range 
-{
+/// Inclusive range of sections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Interval {
     start: u32,
-    end:   u32 
+    end: u32,
 }
 
-fn find_bigger_range(range1: &Range, range2: &Range) -> usize
-{
-    let r1 = range1.end - range1.start;
-    let r2 = range2.end - range2.start;
+impl Interval {
+    fn new(start: u32, end: u32) -> Self {
+        assert!(start <= end, "Empty interval {start}-{end}");
+        Self { start, end }
+    }
+
+    /// Number of sections
+    fn len(self) -> u32 {
+        self.end - self.start + 1
+    }
+
+    fn contains(self, other: Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    fn overlaps(self, other: Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    fn intersection(self, other: Interval) -> Option<Interval> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        Some(Interval::new(
+            self.start.max(other.start),
+            self.end.min(other.end),
+        ))
+    }
+
+    /// The sections of both intervals as a single interval, if they overlap or
+    /// are next to each other.
+    fn union(self, other: Interval) -> Option<Interval> {
+        if self.start > other.end.saturating_add(1) || other.start > self.end.saturating_add(1) {
+            return None;
+        }
+        Some(Interval::new(
+            self.start.min(other.start),
+            self.end.max(other.end),
+        ))
+    }
+
+    /// Sections of `self` that are not in `other`, up to two intervals.
+    fn difference(self, other: Interval) -> Vec<Interval> {
+        if !self.overlaps(other) {
+            return vec![self];
+        }
+
+        let mut ret = vec![];
+        if self.start < other.start {
+            ret.push(Interval::new(self.start, other.start - 1));
+        }
+        if other.end < self.end {
+            ret.push(Interval::new(other.end + 1, self.end));
+        }
+        ret
+    }
 
-    if r1 > r2 { 0 } else { 1 }
+    /// Parses `a-b`, or `a` for a single section
+    fn parse(range: &str, line: usize) -> Result<Self, ParseError> {
+        let invalid = || ParseError::InvalidRange {
+            line,
+            range: range.to_string(),
+        };
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let start: u32 = start.trim().parse().map_err(|_| invalid())?;
+        let end: u32 = end.trim().parse().map_err(|_| invalid())?;
+
+        if start > end {
+            return Err(ParseError::ReversedRange {
+                line,
+                range: range.to_string(),
+            });
+        }
+        Ok(Interval::new(start, end))
+    }
 }
 
-fn is_overlapped(range1: &Range, range2: &Range) -> bool
-{
-    if range1.start <= range2.start && range2.start <= range1.end { return true; }
-    if range1.start <= range2.end && range2.end <= range1.end { return true; }
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
 
-    false
+/// Sections covered by any of many intervals, stored as sorted disjoint
+/// intervals.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct IntervalSet {
+    intervals: Vec<Interval>,
 }
 
-fn part1(input: &Vec<String>) {
-    let mut contained_ranges: u32 = 0;
-    
-    for line in input.iter() {
-        // println!("{}", line);
-        let split = line.split(",");
+impl IntervalSet {
+    fn insert(&mut self, interval: Interval) {
+        // Merge with every interval it overlaps or touches
+        let mut merged = interval;
+        self.intervals.retain(|&other| match merged.union(other) {
+            Some(union) => {
+                merged = union;
+                false
+            }
+            None => true,
+        });
+        let idx = self.intervals.partition_point(|&other| other < merged);
+        self.intervals.insert(idx, merged);
+    }
 
-        // Vec[start, end]
-        let mut elves: Vec<Range> = Vec::new();
-        for range_str in split {
-            let ssplit: Vec<u32> = range_str.split("-").filter_map(|num| num.parse::<u32>().ok()).collect();
-            elves.push(Range{start: ssplit[0], end: ssplit[1]});
-        }
+    /// Number of sections
+    fn len(&self) -> u32 {
+        self.intervals.iter().map(|i| i.len()).sum()
+    }
 
-        let bigger = find_bigger_range(&elves[0], &elves[1]);
-        let smaller = (bigger + 1) % 2;
+    fn iter(&self) -> std::slice::Iter<'_, Interval> {
+        self.intervals.iter()
+    }
+}
 
-        if elves[bigger].start <= elves[smaller].start && elves[bigger].end >= elves[smaller].end {
-            contained_ranges += 1;
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Interval>>(iter: I) -> Self {
+        let mut ret = IntervalSet::default();
+        for interval in iter {
+            ret.insert(interval);
         }
+        ret
     }
+}
 
-    println!("{}", contained_ranges);
+impl fmt::Display for IntervalSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let intervals: Vec<String> = self.iter().map(|i| i.to_string()).collect();
+        write!(f, "{}", intervals.join(","))
+    }
 }
 
-fn part2(input: &Vec<String>) {
-    let mut contained_ranges: u32 = 0;
-    
-    for line in input.iter() {
-        // println!("{}", line);
-        let split = line.split(",");
+/// Sections assigned to at least `k` of the `intervals`
+fn covered_by_at_least(intervals: &[Interval], k: NonZeroUsize) -> IntervalSet {
+    // Sweep over the starts and (exclusive) ends of every interval
+    let mut events: Vec<(u64, i64)> = intervals
+        .iter()
+        .flat_map(|i| [(i.start as u64, 1), (i.end as u64 + 1, -1)])
+        .collect();
+    events.sort();
 
-        // Vec[start, end]
-        let mut elves: Vec<Range> = Vec::new();
-        for range_str in split {
-            let ssplit: Vec<u32> = range_str.split("-").filter_map(|num| num.parse::<u32>().ok()).collect();
-            elves.push(Range{start: ssplit[0], end: ssplit[1]});
+    let mut ret = IntervalSet::default();
+    let mut count = 0;
+    for (i, &(pos, change)) in events.iter().enumerate() {
+        count += change;
+        let next = events.get(i + 1).map_or(pos, |&(next, _)| next);
+        if count >= k.get() as i64 && next > pos {
+            ret.insert(Interval::new(pos as u32, (next - 1) as u32));
         }
+    }
+    ret
+}
+
+/// Assignment of an elf, given by the line (1-based) and index in the line
+type ElfId = (usize, usize);
 
-        let bigger = find_bigger_range(&elves[0], &elves[1]);
-        let smaller = (bigger + 1) % 2;
+/// Assignments contained in the assignment of another elf, anywhere in the
+/// input. Each comes with the elf whose assignment contains it. Elves with the
+/// same assignment are redundant to each other.
+fn redundant_assignments(groups: &[Vec<Interval>]) -> Vec<(ElfId, ElfId)> {
+    let elves: Vec<(ElfId, Interval)> = groups
+        .iter()
+        .enumerate()
+        .flat_map(|(line, group)| {
+            group
+                .iter()
+                .enumerate()
+                .map(move |(i, &r)| ((line + 1, i), r))
+        })
+        .collect();
+
+    elves
+        .iter()
+        .filter_map(|&(id, interval)| {
+            elves
+                .iter()
+                .find(|&&(other, r)| other != id && r.contains(interval))
+                .map(|&(other, _)| (id, other))
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    EmptyLine { line: usize },
+    InvalidRange { line: usize, range: String },
+    ReversedRange { line: usize, range: String },
+}
 
-        if is_overlapped(&elves[bigger], &elves[smaller]) {
-            contained_ranges += 1;
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::EmptyLine { line } => write!(f, "Line {line} has no assignments"),
+            ParseError::InvalidRange { line, range } => write!(
+                f,
+                "Invalid range `{range}` on line {line}, expected `a-b` or `a`"
+            ),
+            ParseError::ReversedRange { line, range } => {
+                write!(f, "Range `{range}` on line {line} ends before it starts")
+            }
         }
     }
+}
+
+/// Parses every line as a group of any number of comma separated ranges
+fn parse_groups(input: &[String]) -> Result<Vec<Vec<Interval>>, ParseError> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, ln)| {
+            if ln.trim().is_empty() {
+                return Err(ParseError::EmptyLine { line: i + 1 });
+            }
+            ln.split(',')
+                .map(|range| Interval::parse(range, i + 1))
+                .collect()
+        })
+        .collect()
+}
+
+/// Sections assigned to the whole group, and for every elf the sections
+/// assigned to no other elf of the group
+fn group_sections(group: &[Interval]) -> (Option<Interval>, Vec<IntervalSet>) {
+    let shared = group
+        .iter()
+        .skip(1)
+        .try_fold(group[0], |acc, &other| acc.intersection(other));
+    let alone = group
+        .iter()
+        .enumerate()
+        .map(|(i, &interval)| {
+            let others = group
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, &other)| other);
+            others
+                .fold(vec![interval], |left, other| {
+                    left.into_iter()
+                        .flat_map(|interval| interval.difference(other))
+                        .collect()
+                })
+                .into_iter()
+                .collect()
+        })
+        .collect();
+    (shared, alone)
+}
 
-    println!("{}", contained_ranges);
+/// Whether a range of the group is fully contained in another one
+fn has_contained(group: &[Interval]) -> bool {
+    group.iter().enumerate().any(|(i, &a)| {
+        group
+            .iter()
+            .enumerate()
+            .any(|(j, &b)| i != j && a.contains(b))
+    })
+}
+
+/// Whether any two ranges of the group overlap
+fn has_overlap(group: &[Interval]) -> bool {
+    // Sorted by start, if a range overlaps any later one it overlaps the next
+    let mut sorted = group.to_vec();
+    sorted.sort();
+    sorted.windows(2).any(|w| w[0].overlaps(w[1]))
+}
+
+fn part1(input: &[String]) {
+    match parse_groups(input) {
+        Ok(groups) => println!("{}", groups.iter().filter(|g| has_contained(g)).count()),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+fn part2(input: &[String]) {
+    match parse_groups(input) {
+        Ok(groups) => println!("{}", groups.iter().filter(|g| has_overlap(g)).count()),
+        Err(e) => eprintln!("{e}"),
+    }
 }
 
 fn main() {
@@ -79,13 +297,164 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: Vec<String> = match read_inputs(&file_path) {
         Ok(input) => input,
         Err(e) => panic!("Error parsing the inputs: {:?}", e)
     };
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
+
+    let coverage = match flag_value("--coverage").map(|k| k.parse::<NonZeroUsize>()) {
+        None => None,
+        Some(Ok(k)) => Some(k),
+        Some(Err(_)) => {
+            eprintln!("`--coverage` expects a number of elves above 0");
+            return;
+        }
+    };
+    if coverage.is_none() && !has_flag("--redundant") && !has_flag("--sections") {
+        return;
+    }
+    let groups = match parse_groups(&input) {
+        Ok(groups) => groups,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    if let Some(k) = coverage {
+        let intervals: Vec<Interval> = groups.iter().flatten().copied().collect();
+        let covered = covered_by_at_least(&intervals, k);
+        println!(
+            "\nSections covered by at least {k} elves ({}):",
+            covered.len()
+        );
+        println!("{covered}");
+    }
+    if has_flag("--redundant") {
+        let redundant = redundant_assignments(&groups);
+        println!("\nRedundant assignments ({}):", redundant.len());
+        for ((line, i), (other_line, j)) in redundant {
+            println!(
+                "line {line} elf {}: within line {other_line} elf {}",
+                i + 1,
+                j + 1
+            );
+        }
+    }
+    if has_flag("--sections") {
+        println!("\nSections of every group:");
+        for (line, group) in groups.iter().enumerate() {
+            let (shared, alone) = group_sections(group);
+            let shared = shared.map_or("none".to_string(), |i| i.to_string());
+            let alone: Vec<String> = alone
+                .iter()
+                .enumerate()
+                .map(|(i, set)| match set.iter().next() {
+                    None => format!("elf {} alone none", i + 1),
+                    Some(_) => format!("elf {} alone {set}", i + 1),
+                })
+                .collect();
+            println!("line {}: shared {shared}, {}", line + 1, alone.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interval_algebra() {
+        let a = Interval::new(2, 6);
+        let b = Interval::new(4, 8);
+        assert!(a.overlaps(b) && !a.contains(b));
+        assert!(a.contains(Interval::new(3, 6)));
+        assert_eq!(a.intersection(b), Some(Interval::new(4, 6)));
+        assert_eq!(a.intersection(Interval::new(7, 9)), None);
+        assert_eq!(a.union(Interval::new(7, 9)), Some(Interval::new(2, 9)));
+        assert_eq!(a.union(Interval::new(8, 9)), None);
+        assert_eq!(a.difference(b), [Interval::new(2, 3)]);
+        assert_eq!(
+            b.difference(Interval::new(5, 6)),
+            [Interval::new(4, 4), Interval::new(7, 8)]
+        );
+        assert_eq!(a.difference(Interval::new(1, 9)), []);
+    }
+
+    #[test]
+    fn interval_set() {
+        let set: IntervalSet = [(5, 7), (1, 2), (10, 12), (3, 3), (6, 11)]
+            .into_iter()
+            .map(|(s, e)| Interval::new(s, e))
+            .collect();
+        assert_eq!(set.to_string(), "1-3,5-12");
+        assert_eq!(set.len(), 11);
+    }
+
+    #[test]
+    fn coverage() {
+        let intervals: Vec<Interval> = [(2, 4), (6, 8), (2, 3), (4, 5), (3, 7)]
+            .into_iter()
+            .map(|(s, e)| Interval::new(s, e))
+            .collect();
+        let covered = |k| covered_by_at_least(&intervals, NonZeroUsize::new(k).unwrap());
+        assert_eq!(covered(1).to_string(), "2-8");
+        assert_eq!(covered(2).to_string(), "2-7");
+        assert_eq!(covered(3).to_string(), "3-4");
+        assert_eq!(covered(4).to_string(), "");
+    }
+
+    #[test]
+    fn sections() {
+        let group = [
+            Interval::new(2, 6),
+            Interval::new(4, 8),
+            Interval::new(5, 5),
+        ];
+        let (shared, alone) = group_sections(&group);
+        assert_eq!(shared, Some(Interval::new(5, 5)));
+        let alone: Vec<String> = alone.iter().map(|set| set.to_string()).collect();
+        assert_eq!(alone, ["2-3", "7-8", ""]);
+        assert_eq!(group_sections(&group[..1]).0, Some(group[0]));
+        assert_eq!(
+            group_sections(&[Interval::new(1, 2), Interval::new(3, 4)]).0,
+            None
+        );
+    }
+
+    #[test]
+    fn group_parsing() {
+        let input: Vec<String> = ["2-4,6-8", "5, 1-3 ,4-9", "7"]
+            .iter()
+            .map(|ln| ln.to_string())
+            .collect();
+        let groups = parse_groups(&input).unwrap();
+        assert_eq!(
+            groups[1],
+            [
+                Interval::new(5, 5),
+                Interval::new(1, 3),
+                Interval::new(4, 9)
+            ]
+        );
+        assert!(!has_contained(&groups[0]) && !has_overlap(&groups[0]));
+        assert!(has_contained(&groups[1]) && has_overlap(&groups[1]));
+        assert!(!has_contained(&groups[2]) && !has_overlap(&groups[2]));
+
+        let error = |line: &str| parse_groups(&[line.to_string()]).unwrap_err().to_string();
+        assert_eq!(
+            error("2-4,6-x"),
+            "Invalid range `6-x` on line 1, expected `a-b` or `a`"
+        );
+        assert_eq!(
+            error("2-4,,6-8"),
+            "Invalid range `` on line 1, expected `a-b` or `a`"
+        );
+        assert_eq!(error("4-2"), "Range `4-2` on line 1 ends before it starts");
+        assert_eq!(error(""), "Line 1 has no assignments");
+    }
 }
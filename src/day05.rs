@@ -1,114 +1,361 @@
-use aoc::read_inputs;
+use aoc::{flag_value, has_flag, input_arg, read_inputs, run_part};
+use std::fmt;
+use std::num::NonZeroUsize;
 
+type Stack = Vec<char>;
 
-fn part1(input: &Vec<String>) 
-{
-    let mut boxes: Vec<String> = Vec::new();
-    let mut moves_idx = 0;
-    for (i, line) in input.iter().enumerate() {
-        if line == "" {
-            moves_idx = i + 1;
-            break;
-        }
-        boxes.push(line.to_string());
+/// Move `count` crates from stack `from` to stack `to`, both 0-based
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Move {
+    count: usize,
+    from: usize,
+    to: usize,
+}
+
+trait Crane {
+    /// Order, bottom to top, in which the crates lifted from a stack (also
+    /// bottom to top) end up on the destination stack
+    fn arrange(&self, lifted: Vec<char>) -> Vec<char>;
+}
+
+/// Moves one crate at a time
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn arrange(&self, mut lifted: Vec<char>) -> Vec<char> {
+        lifted.reverse();
+        lifted
     }
+}
+
+/// Moves all the crates at once
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn arrange(&self, lifted: Vec<char>) -> Vec<char> {
+        lifted
+    }
+}
 
-    let nbox: usize = boxes.last().unwrap()
-            .chars().rev().nth(1).unwrap()
-            .to_digit(10).unwrap()
-            .try_into().unwrap();
-    
-    let mut box_stacks: Vec<Vec<char>> = Vec::with_capacity(nbox);
-    for _ in 0..nbox {
-        box_stacks.push(vec![]);
+/// Moves up to `capacity` crates at a time
+struct LimitedCrane {
+    capacity: NonZeroUsize,
+}
+
+impl Crane for LimitedCrane {
+    fn arrange(&self, lifted: Vec<char>) -> Vec<char> {
+        lifted
+            .rchunks(self.capacity.get())
+            .flatten()
+            .copied()
+            .collect()
     }
+}
 
-    for row in (0..boxes.len()-1).rev() {
-        let mut i = 0;
-        while i < (boxes[row].len() + 1)/4 {
-            let box_letter = boxes[row].chars().nth(4*i + 1).unwrap();
-            if box_letter != ' ' {
-                box_stacks[i].push(box_letter);
+#[derive(Debug, PartialEq)]
+enum MoveError {
+    NoSuchStack {
+        stack: usize,
+        stacks: usize,
+    },
+    NotEnoughCrates {
+        stack: usize,
+        crates: usize,
+        count: usize,
+    },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::NoSuchStack { stack, stacks } => {
+                write!(f, "There is no stack {}, only {stacks}", stack + 1)
             }
-            i += 1;
+            MoveError::NotEnoughCrates {
+                stack,
+                crates,
+                count,
+            } => write!(
+                f,
+                "Can't move {count} crates from stack {}, it only has {crates}",
+                stack + 1
+            ),
         }
     }
-    // println!("{:?}", box_stacks);
+}
 
-    for line in input[moves_idx..].iter() {
-        let move_op: Vec<usize> = line.split(" ")
-                .filter_map(|num| usize::from_str_radix(num, 10).ok())
-                .collect();
-        
-        // println!("{:?}", move_op);
+struct CrateYard {
+    stacks: Vec<Stack>,
+    crane: Box<dyn Crane>,
+    // Every move done, with the crates it lifted
+    history: Vec<(Move, Vec<char>)>,
+}
 
-        for _ in 0..move_op[0] {
-            let box_unloaded = box_stacks[move_op[1]-1].pop().unwrap();
-            box_stacks[move_op[2]-1].push(box_unloaded);
+impl CrateYard {
+    fn new(stacks: Vec<Stack>, crane: Box<dyn Crane>) -> Self {
+        Self {
+            stacks,
+            crane,
+            history: vec![],
         }
-        
-        // println!("{:?}", box_stacks)
     }
 
-    for i in box_stacks.iter() {
-        print!("{0}", i.last().unwrap())
+    fn apply(&mut self, mv: Move) -> Result<(), MoveError> {
+        for stack in [mv.from, mv.to] {
+            if stack >= self.stacks.len() {
+                return Err(MoveError::NoSuchStack {
+                    stack,
+                    stacks: self.stacks.len(),
+                });
+            }
+        }
+        let crates = self.stacks[mv.from].len();
+        if crates < mv.count {
+            return Err(MoveError::NotEnoughCrates {
+                stack: mv.from,
+                crates,
+                count: mv.count,
+            });
+        }
+
+        let lifted: Vec<char> = self.stacks[mv.from].drain(crates - mv.count..).collect();
+        let arranged = self.crane.arrange(lifted.clone());
+        self.stacks[mv.to].extend(arranged);
+        self.history.push((mv, lifted));
+        Ok(())
+    }
+
+    /// Undoes the last move, returning it
+    fn undo(&mut self) -> Option<Move> {
+        let (mv, lifted) = self.history.pop()?;
+        let len = self.stacks[mv.to].len();
+        self.stacks[mv.to].truncate(len - mv.count);
+        self.stacks[mv.from].extend(lifted);
+        Some(mv)
+    }
+
+    /// Crates of every stack, from the bottom
+    pub fn stacks(&self) -> &[Stack] {
+        &self.stacks
+    }
+
+    /// Crate on top of every stack, skipping empty stacks
+    fn tops(&self) -> String {
+        self.stacks().iter().filter_map(|s| s.last()).collect()
     }
-    println!();
 }
 
+impl fmt::Display for CrateYard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", render(self.stacks()))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    MissingLabels,
+    InvalidLabel { label: String, expected: usize },
+    MalformedCrate { line: usize, column: usize },
+    CrateWithoutStack { line: usize, column: usize },
+    FloatingCrate { line: usize, column: usize },
+    InvalidMove { line: usize, text: String },
+}
 
-fn part2(input: &Vec<String>) 
-{
-    let mut boxes: Vec<String> = Vec::new();
-    let mut moves_idx = 0;
-    for (i, line) in input.iter().enumerate() {
-        if line == "" {
-            moves_idx = i + 1;
-            break;
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::MissingLabels => write!(f, "The drawing has no row of stack labels"),
+            ParseError::InvalidLabel { label, expected } => {
+                write!(f, "Invalid stack label `{label}`, expected `{expected}`")
+            }
+            ParseError::MalformedCrate { line, column } => {
+                write!(
+                    f,
+                    "Expected a crate like `[A]` on line {line}, column {column}"
+                )
+            }
+            ParseError::CrateWithoutStack { line, column } => write!(
+                f,
+                "Crate on line {line}, column {column} is past the last stack"
+            ),
+            ParseError::FloatingCrate { line, column } => write!(
+                f,
+                "Crate on line {line}, column {column} has nothing below it"
+            ),
+            ParseError::InvalidMove { line, text } => write!(
+                f,
+                "Invalid move `{text}` on line {line}, expected `move N from A to B`"
+            ),
+        }
+    }
+}
+
+impl Move {
+    /// Parses `move N from A to B`, with `A` and `B` 1-based
+    fn parse(text: &str, line: usize) -> Result<Self, ParseError> {
+        let invalid = || ParseError::InvalidMove {
+            line,
+            text: text.to_string(),
+        };
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let ["move", count, "from", from, "to", to] = words[..] else {
+            return Err(invalid());
+        };
+        let number = |n: &str| n.parse::<usize>().map_err(|_| invalid());
+        let (count, from, to) = (number(count)?, number(from)?, number(to)?);
+        if from == 0 || to == 0 {
+            return Err(invalid());
         }
-        boxes.push(line.to_string());
+        Ok(Move {
+            count,
+            from: from - 1,
+            to: to - 1,
+        })
     }
+}
 
-    let nbox: usize = boxes.last().unwrap()
-            .chars().rev().nth(1).unwrap()
-            .to_digit(10).unwrap()
-            .try_into().unwrap();
-    
-    let mut box_stacks: Vec<Vec<char>> = Vec::with_capacity(nbox);
-    for _ in 0..nbox {
-        box_stacks.push(vec![]);
+/// Parses the drawing of the stacks, ending with a row of labels `1` to `n`.
+/// Crates are `[A]` cells 4 columns apart; lines may be cut short.
+fn parse_drawing(drawing: &[String]) -> Result<Vec<Stack>, ParseError> {
+    let (labels, rows) = drawing.split_last().ok_or(ParseError::MissingLabels)?;
+    let mut nstacks = 0;
+    for label in labels.split_whitespace() {
+        nstacks += 1;
+        if label.parse() != Ok(nstacks) {
+            return Err(ParseError::InvalidLabel {
+                label: label.to_string(),
+                expected: nstacks,
+            });
+        }
+    }
+    if nstacks == 0 {
+        return Err(ParseError::MissingLabels);
     }
 
-    for row in (0..boxes.len()-1).rev() {
-        let mut i = 0;
-        while i < (boxes[row].len() + 1)/4 {
-            let box_letter = boxes[row].chars().nth(4*i + 1).unwrap();
-            if box_letter != ' ' {
-                box_stacks[i].push(box_letter);
+    let mut stacks: Vec<Stack> = vec![vec![]; nstacks];
+    // Bottom row first, so every crate must land right on top of its stack
+    for (height, (idx, row)) in rows.iter().enumerate().rev().enumerate() {
+        let line = idx + 1;
+        let bytes = row.as_bytes();
+        let mut col = 0;
+        while col < bytes.len() {
+            if bytes[col] == b' ' {
+                col += 1;
+                continue;
+            }
+            let column = col + 1;
+            let label = match bytes.get(col..col + 3) {
+                Some(&[b'[', c, b']']) if col % 4 == 0 && c.is_ascii_graphic() => c as char,
+                _ => return Err(ParseError::MalformedCrate { line, column }),
+            };
+            let stack = stacks
+                .get_mut(col / 4)
+                .ok_or(ParseError::CrateWithoutStack { line, column })?;
+            if stack.len() != height {
+                return Err(ParseError::FloatingCrate { line, column });
             }
-            i += 1;
+            stack.push(label);
+            col += 3;
         }
     }
-    // println!("{:?}", box_stacks);
+    Ok(stacks)
+}
 
-    for line in input[moves_idx..].iter() {
-        let move_op: Vec<usize> = line.split(" ")
-                .filter_map(|num| usize::from_str_radix(num, 10).ok())
+/// Draws the stacks the way the puzzle input does
+fn render(stacks: &[Stack]) -> String {
+    let height = stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut lines: Vec<String> = (0..height)
+        .rev()
+        .map(|h| {
+            let cells: Vec<String> = stacks
+                .iter()
+                .map(|s| s.get(h).map_or("   ".to_string(), |c| format!("[{c}]")))
                 .collect();
-        
-        // println!("{:?}", move_op);
+            cells.join(" ")
+        })
+        .collect();
+    let labels: Vec<String> = (1..=stacks.len()).map(|i| format!("{i:^3}")).collect();
+    lines.push(labels.join(" "));
+    lines.join("\n")
+}
+
+/// The stacks, and the moves after the blank line
+fn parse_input(input: &[String]) -> Result<(Vec<Stack>, Vec<Move>), ParseError> {
+    let split = input
+        .iter()
+        .position(|ln| ln.trim().is_empty())
+        .unwrap_or(input.len());
+    let stacks = parse_drawing(&input[..split])?;
+    let moves = input
+        .iter()
+        .enumerate()
+        .skip(split + 1)
+        .filter(|(_, ln)| !ln.trim().is_empty())
+        .map(|(i, ln)| Move::parse(ln, i + 1))
+        .collect::<Result<_, _>>()?;
+    Ok((stacks, moves))
+}
 
-        let unload_range = (box_stacks[move_op[1]-1].len()-move_op[0])..;
-        let boxes_unloaded: Vec<char> = box_stacks[move_op[1]-1].drain(unload_range).collect();
-        box_stacks[move_op[2]-1].extend_from_slice(&boxes_unloaded);
-        
-        // println!("{:?}", box_stacks)
+/// The yard after doing every move with `crane`
+fn arrange(
+    stacks: Vec<Stack>,
+    moves: &[Move],
+    crane: Box<dyn Crane>,
+) -> Result<CrateYard, MoveError> {
+    let mut yard = CrateYard::new(stacks, crane);
+    for &mv in moves {
+        yard.apply(mv)?;
     }
+    Ok(yard)
+}
 
-    for i in box_stacks.iter() {
-        print!("{0}", i.last().unwrap())
+/// Prints the top crates after doing every move with `crane`, and the whole
+/// yard with `--draw`. With `--undo=N` the last N moves are then undone and
+/// the crates of every stack are listed, or the yard drawn with `--draw`.
+fn solve(input: &[String], crane: Box<dyn Crane>) {
+    let (stacks, moves) = match parse_input(input) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    let mut yard = match arrange(stacks, &moves, crane) {
+        Ok(yard) => yard,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    println!("{}", yard.tops());
+    if has_flag("--draw") {
+        println!("{yard}");
     }
-    println!();
+
+    let Some(count) = flag_value("--undo") else {
+        return;
+    };
+    let Ok(count) = count.parse::<usize>() else {
+        eprintln!("`--undo` expects a number of moves");
+        return;
+    };
+    let undone = (0..count).map_while(|_| yard.undo()).count();
+    println!("After undoing {undone} moves: {}", yard.tops());
+    if has_flag("--draw") {
+        println!("{yard}");
+    } else {
+        for (i, stack) in yard.stacks().iter().enumerate() {
+            println!("{}: {}", i + 1, stack.iter().collect::<String>());
+        }
+    }
+}
+
+fn part1(input: &[String]) {
+    solve(input, Box::new(CrateMover9000));
+}
+
+fn part2(input: &[String]) {
+    solve(input, Box::new(CrateMover9001));
 }
 
 fn main() {
@@ -118,13 +365,159 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: Vec<String> = match read_inputs(&file_path) {
         Ok(input) => input,
         Err(e) => panic!("Error parsing the inputs: {:?}", e)
     };
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
+
+    if let Some(capacity) = flag_value("--capacity") {
+        match capacity.parse::<NonZeroUsize>() {
+            Ok(capacity) => run_part(&format!("CAPACITY {capacity}"), || {
+                solve(&input, Box::new(LimitedCrane { capacity }))
+            }),
+            Err(_) => eprintln!("`--capacity` expects a number of crates above 0"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    fn example() -> (Vec<Stack>, Vec<Move>) {
+        let input = std::fs::read_to_string("data/examples/05.txt").unwrap();
+        parse_input(&lines(&input)).unwrap()
+    }
+
+    #[test]
+    fn cranes() {
+        let lifted = vec!['A', 'B', 'C', 'D', 'E'];
+        assert_eq!(
+            CrateMover9000.arrange(lifted.clone()),
+            ['E', 'D', 'C', 'B', 'A']
+        );
+        assert_eq!(CrateMover9001.arrange(lifted.clone()), lifted);
+        let crane = LimitedCrane {
+            capacity: NonZeroUsize::new(2).unwrap(),
+        };
+        assert_eq!(crane.arrange(lifted.clone()), ['D', 'E', 'B', 'C', 'A']);
+        let crane = LimitedCrane {
+            capacity: NonZeroUsize::MIN,
+        };
+        assert_eq!(
+            crane.arrange(lifted.clone()),
+            CrateMover9000.arrange(lifted)
+        );
+    }
+
+    #[test]
+    fn undo_moves() {
+        let (stacks, moves) = example();
+        let crane = LimitedCrane {
+            capacity: NonZeroUsize::new(2).unwrap(),
+        };
+        let mut yard = CrateYard::new(stacks.clone(), Box::new(crane));
+
+        let mut states = vec![yard.stacks().to_vec()];
+        for &mv in moves.iter() {
+            yard.apply(mv).unwrap();
+            states.push(yard.stacks().to_vec());
+        }
+        assert_eq!(yard.tops(), "MCZ");
+
+        for &mv in moves.iter().rev() {
+            states.pop();
+            assert_eq!(yard.undo(), Some(mv));
+            assert_eq!(yard.stacks(), states.last().unwrap());
+        }
+        assert_eq!(yard.undo(), None);
+        assert_eq!(yard.stacks(), stacks);
+    }
+
+    #[test]
+    fn invalid_moves() {
+        let (stacks, _) = example();
+        let mut yard = CrateYard::new(stacks, Box::new(CrateMover9000));
+        let mv = |count, from, to| Move { count, from, to };
+        assert_eq!(
+            yard.apply(mv(1, 3, 0)),
+            Err(MoveError::NoSuchStack {
+                stack: 3,
+                stacks: 3
+            })
+        );
+        assert_eq!(
+            yard.apply(mv(4, 0, 1)),
+            Err(MoveError::NotEnoughCrates {
+                stack: 0,
+                crates: 2,
+                count: 4
+            })
+        );
+        assert_eq!(yard.tops(), "NDP");
+    }
+
+    #[test]
+    fn drawing_round_trip() {
+        let input = std::fs::read_to_string("data/examples/05.txt").unwrap();
+        let drawing = input.split("\n\n").next().unwrap();
+        let stacks = parse_drawing(&lines(drawing)).unwrap();
+        assert_eq!(stacks, [vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]);
+        assert_eq!(render(&stacks), drawing);
+
+        // Trimmed lines and more than 9 stacks
+        let stacks: Vec<Stack> = (b'A'..=b'L')
+            .map(|c| vec![c as char; c as usize % 3])
+            .collect();
+        let drawing = render(&stacks);
+        assert!(drawing.ends_with(" 9  10  11  12 "));
+        let trimmed: Vec<String> = drawing
+            .lines()
+            .map(|ln| ln.trim_end().to_string())
+            .collect();
+        assert_eq!(parse_drawing(&trimmed).unwrap(), stacks);
+        assert_eq!(parse_drawing(&lines(&drawing)).unwrap(), stacks);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |text: &str| parse_input(&lines(text)).unwrap_err().to_string();
+        assert_eq!(error(""), "The drawing has no row of stack labels");
+        assert_eq!(
+            error("[A]\n 1   3"),
+            "Invalid stack label `3`, expected `2`"
+        );
+        assert_eq!(
+            error(" [A]\n 1   2"),
+            "Expected a crate like `[A]` on line 1, column 2"
+        );
+        assert_eq!(
+            error("[A] [B\n 1   2"),
+            "Expected a crate like `[A]` on line 1, column 5"
+        );
+        assert_eq!(
+            error("[A] [B]\n 1"),
+            "Crate on line 1, column 5 is past the last stack"
+        );
+        assert_eq!(
+            error("[A] [B]\n[C]\n 1   2"),
+            "Crate on line 1, column 5 has nothing below it"
+        );
+        assert_eq!(
+            error("[A]\n 1\n\nmove 1 from 1 to 0"),
+            "Invalid move `move 1 from 1 to 0` on line 4, expected `move N from A to B`"
+        );
+        assert_eq!(
+            error("[A]\n 1\n\nmove 1 from 1"),
+            "Invalid move `move 1 from 1` on line 4, expected `move N from A to B`"
+        );
+    }
 }
@@ -1,53 +1,373 @@
-use aoc::read_inputs;
+use aoc::{flag_value, has_flag, input_arg, run_part};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
 
-fn are_all_char_different(slice: &str) -> bool {
-    for i in 0..slice.len() {
-        for j in i+1..slice.len() {
-            if slice.chars().nth(i).unwrap() == slice.chars().nth(j).unwrap() { 
-                return false; 
+/// Finds the windows of `size` different bytes in a stream, in O(1) per byte
+struct MarkerDetector {
+    size: usize,
+    window: VecDeque<u8>,
+    // Occurrences of every byte in the window
+    counts: [usize; 256],
+    // Number of bytes found more than once in the window
+    repeated: usize,
+    read: usize,
+}
+
+impl MarkerDetector {
+    fn new(size: usize) -> Self {
+        assert!(size > 0, "Markers must be at least one byte long");
+        Self {
+            size,
+            window: VecDeque::with_capacity(size + 1),
+            counts: [0; 256],
+            repeated: 0,
+            read: 0,
+        }
+    }
+
+    /// Feeds the next byte of the stream. If it completes a marker, returns
+    /// the number of bytes read so far.
+    fn push(&mut self, byte: u8) -> Option<usize> {
+        self.read += 1;
+        self.window.push_back(byte);
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 2 {
+            self.repeated += 1;
+        }
+
+        if self.window.len() > self.size {
+            let old = self.window.pop_front().unwrap() as usize;
+            self.counts[old] -= 1;
+            if self.counts[old] == 1 {
+                self.repeated -= 1;
             }
         }
+
+        (self.window.len() == self.size && self.repeated == 0).then_some(self.read)
     }
 
-    return true;
+    /// Forgets the window, so the next marker only uses bytes pushed after this
+    fn reset(&mut self) {
+        self.window.clear();
+        self.counts = [0; 256];
+        self.repeated = 0;
+    }
 }
 
+/// End position of every marker of `size` bytes in the first line read from
+/// `reader`, without loading it whole
+fn markers(reader: impl Read, size: usize) -> impl Iterator<Item = io::Result<usize>> {
+    let mut detector = MarkerDetector::new(size);
+    BufReader::new(reader)
+        .bytes()
+        .take_while(|byte| !matches!(byte, Ok(b'\n')))
+        .filter_map(move |byte| match byte {
+            Ok(byte) => detector.push(byte).map(Ok),
+            Err(e) => Some(Err(e)),
+        })
+}
 
-fn part1(input: &String) {
-    let mut char_idx: usize = 4;
-    while char_idx < input.len() {
-        if are_all_char_different(&input[char_idx-4..char_idx]) {
-            break;
+#[derive(Debug)]
+enum DecodeError {
+    Io(io::Error),
+    NoPacketMarker { size: usize },
+    NoMessageMarker { frame: usize, size: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Io(e) => write!(f, "Can't read the datastream: {e}"),
+            DecodeError::NoPacketMarker { size } => write!(
+                f,
+                "No start-of-packet marker of {size} different characters"
+            ),
+            DecodeError::NoMessageMarker { frame, size } => write!(
+                f,
+                "Frame at {frame} has no start-of-message marker of {size} different characters"
+            ),
         }
-        char_idx += 1;
     }
+}
 
-    println!("{}", char_idx);
+impl From<io::Error> for DecodeError {
+    fn from(e: io::Error) -> Self {
+        DecodeError::Io(e)
+    }
 }
 
-fn part2(input: &String) {
-    let mut char_idx: usize = 14;
-    while char_idx < input.len() {
-        if are_all_char_different(&input[char_idx-14..char_idx]) {
-            break;
+/// A frame starts after a start-of-packet marker with a header, up to a
+/// start-of-message marker. Its payload follows, up to the next
+/// start-of-packet marker or the end of the stream.
+#[derive(Debug, PartialEq)]
+struct Frame {
+    /// Position in the stream of the first header byte
+    start: usize,
+    header: Vec<u8>,
+    payload: Vec<u8>,
+}
+
+/// Marker sizes of the framing protocol
+#[derive(Debug, Clone, Copy)]
+struct Protocol {
+    packet: usize,
+    message: usize,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self {
+            packet: 4,
+            message: 14,
+        }
+    }
+}
+
+impl Protocol {
+    /// Splits the first line read from `reader` into frames, dropping
+    /// whatever comes before the first start-of-packet marker. Markers are
+    /// only looked for in bytes after the previous one.
+    fn frames(&self, reader: impl Read) -> Result<Vec<Frame>, DecodeError> {
+        let mut packet = MarkerDetector::new(self.packet);
+        let mut message = MarkerDetector::new(self.message);
+        let mut frames: Vec<Frame> = vec![];
+        let mut in_payload = false;
+        for (pos, byte) in BufReader::new(reader).bytes().enumerate() {
+            let byte = byte?;
+            if byte == b'\n' {
+                break;
+            }
+
+            let Some(frame) = frames.last_mut() else {
+                if packet.push(byte).is_some() {
+                    frames.push(Frame::new(pos + 1));
+                }
+                continue;
+            };
+            if !in_payload {
+                frame.header.push(byte);
+                if message.push(byte).is_some() {
+                    frame.header.truncate(frame.header.len() - self.message);
+                    packet.reset();
+                    in_payload = true;
+                }
+            } else {
+                frame.payload.push(byte);
+                if packet.push(byte).is_some() {
+                    frame.payload.truncate(frame.payload.len() - self.packet);
+                    frames.push(Frame::new(pos + 1));
+                    message.reset();
+                    in_payload = false;
+                }
+            }
+        }
+
+        match frames.last() {
+            None => Err(DecodeError::NoPacketMarker { size: self.packet }),
+            Some(frame) if !in_payload => Err(DecodeError::NoMessageMarker {
+                frame: frame.start,
+                size: self.message,
+            }),
+            Some(_) => Ok(frames),
         }
-        char_idx += 1;
     }
 
-    println!("{}", char_idx);
+    /// Payload of every frame
+    fn decode(&self, reader: impl Read) -> Result<Vec<Vec<u8>>, DecodeError> {
+        let frames = self.frames(reader)?;
+        Ok(frames.into_iter().map(|frame| frame.payload).collect())
+    }
+}
+
+impl Frame {
+    fn new(start: usize) -> Self {
+        Self {
+            start,
+            header: vec![],
+            payload: vec![],
+        }
+    }
+}
+
+/// Prints every frame with its header and payload
+fn print_frames(file_path: &str, protocol: Protocol) {
+    let frames = File::open(file_path)
+        .map_err(DecodeError::from)
+        .and_then(|file| protocol.frames(file));
+    let frames = match frames {
+        Ok(frames) => frames,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    println!("\n{} frames:", frames.len());
+    for frame in frames {
+        println!(
+            "{}: header `{}`, payload `{}`",
+            frame.start,
+            String::from_utf8_lossy(&frame.header),
+            String::from_utf8_lossy(&frame.payload)
+        );
+    }
+}
+
+/// Prints the payload of every frame
+fn print_payloads(file_path: &str, protocol: Protocol) {
+    let payloads = File::open(file_path)
+        .map_err(DecodeError::from)
+        .and_then(|file| protocol.decode(file));
+    match payloads {
+        Ok(payloads) => {
+            println!("\n{} payloads:", payloads.len());
+            for payload in payloads {
+                println!("{}", String::from_utf8_lossy(&payload));
+            }
+        }
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+/// Prints the end of the first marker of `size` bytes, or all of them with
+/// `--all`
+fn find_markers(file_path: &str, size: usize) {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Can't open {file_path}: {e}");
+            return;
+        }
+    };
+
+    let mut found = false;
+    for marker in markers(file, size) {
+        match marker {
+            Ok(pos) => println!("{pos}"),
+            Err(e) => {
+                eprintln!("Can't read {file_path}: {e}");
+                return;
+            }
+        }
+        found = true;
+        if !has_flag("--all") {
+            break;
+        }
+    }
+    if !found {
+        eprintln!("No marker of {size} different characters");
+    }
 }
 
 fn main() {
-    let day = 06;
+    let day = 6;
     #[cfg(debug_assertions)]
     let file_path = format!("data/examples/{:02}.txt", day);
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
+
+    let mut protocol = Protocol::default();
+    let sizes = [
+        ("--packet", &mut protocol.packet),
+        ("--message", &mut protocol.message),
+    ];
+    for (flag, size) in sizes {
+        let Some(value) = flag_value(flag) else {
+            continue;
+        };
+        match value.parse() {
+            Ok(n) if n > 0 => *size = n,
+            _ => {
+                eprintln!("`{flag}` expects a marker size above 0");
+                return;
+            }
+        }
+    }
+    run_part("PART 1", || find_markers(&file_path, protocol.packet));
+    run_part("PART 2", || find_markers(&file_path, protocol.message));
+
+    if has_flag("--decode") {
+        print_frames(&file_path, protocol);
+    }
+    if has_flag("--payloads") {
+        print_payloads(&file_path, protocol);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn all_markers(stream: &str, size: usize) -> Vec<usize> {
+        markers(stream.as_bytes(), size)
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn first_markers() {
+        let examples = [
+            ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+        ];
+        for (stream, packet, message) in examples {
+            assert_eq!(all_markers(stream, 4)[0], packet);
+            assert_eq!(all_markers(stream, 14)[0], message);
+        }
+    }
+
+    #[test]
+    fn every_marker() {
+        assert_eq!(all_markers("aabcdbbc", 3), [4, 5, 6]);
+        assert_eq!(all_markers("abab\nabc", 3), []);
+        assert_eq!(all_markers("aaaa", 1), [1, 2, 3, 4]);
+        assert_eq!(all_markers("", 4), []);
 
-    let input: Vec<String> = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input[0]);
-    println!("PART 2:");
-    part2(&input[0]);
+        // Not the O(n * k) way
+        let stream: String = (0..1_000_000)
+            .map(|i| (b'a' + (i % 26) as u8) as char)
+            .collect();
+        assert_eq!(all_markers(&stream, 26).len(), 1_000_000 - 25);
+    }
+
+    #[test]
+    fn decoding() {
+        let protocol = Protocol {
+            packet: 3,
+            message: 4,
+        };
+        // Preamble, packet marker, header, message marker, payload, and again
+        let stream = "aa abc xxwxyz kklla abc bbabcd zz".replace(' ', "");
+        let frame = |start, header: &str, payload: &str| Frame {
+            start,
+            header: header.as_bytes().to_vec(),
+            payload: payload.as_bytes().to_vec(),
+        };
+        assert_eq!(
+            protocol.frames(stream.as_bytes()).unwrap(),
+            [frame(5, "xx", "kklla"), frame(19, "bb", "zz")]
+        );
+        assert_eq!(
+            protocol.decode(stream.as_bytes()).unwrap(),
+            [b"kklla".to_vec(), b"zz".to_vec()]
+        );
+
+        let error = |stream: &str| protocol.decode(stream.as_bytes()).unwrap_err().to_string();
+        assert_eq!(
+            error("aabbaab"),
+            "No start-of-packet marker of 3 different characters"
+        );
+        assert_eq!(
+            error("abcaabb"),
+            "Frame at 3 has no start-of-message marker of 4 different characters"
+        );
+        assert!(Protocol::default().decode("".as_bytes()).is_err());
+    }
 }
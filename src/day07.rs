@@ -1,234 +1,728 @@
-use std::{cell::RefCell, rc::Rc};
+use aoc::{flag_value, has_flag, input_arg, read_inputs, run_part};
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
 
-use aoc::read_inputs;
+/// Index of a node in the filesystem arena
+type NodeId = usize;
 
-type DirectoryRef = Rc<RefCell<Directory>>;
+const ROOT: NodeId = 0;
 
-struct File {
-    name: String,
-    size: u32
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Dir,
+    File,
 }
 
-struct Directory {
+struct Node {
     name: String,
-    parent: Option<DirectoryRef>,
-    subdirs: Vec<DirectoryRef>,
-    files: Vec<File>,
-    size: u32
-}
-
-impl Directory {
-    pub fn new(name: String, parent: Option<DirectoryRef>) -> Self {
-        return Self { 
-            name: name,
-            parent: parent,
-            subdirs: vec![],
-            files: vec![],
-            size: 0
-        };
-    }
+    parent: Option<NodeId>,
+    kind: Kind,
+    children: Vec<NodeId>,
+    /// Size of the file, or of everything below the directory
+    size: u64,
+}
+
+/// Tree of directories and files, stored in a single vector with the root
+/// first
+struct FileSystem {
+    nodes: Vec<Node>,
+}
 
-    pub fn new_root() -> Self {
-        return Self {
+impl FileSystem {
+    fn new() -> Self {
+        let root = Node {
             name: String::from("/"),
             parent: None,
-            subdirs: vec![],
-            files: vec![],
-            size: 0
+            kind: Kind::Dir,
+            children: vec![],
+            size: 0,
         };
+        Self { nodes: vec![root] }
     }
 
-    pub fn is_subdir(&self, name: &str) -> bool {
-        for dir in self.subdirs.iter() {
-            if &dir.try_borrow().unwrap().name == name {
-                return true;
-            }
-        }
+    fn name(&self, id: NodeId) -> &str {
+        &self.nodes[id].name
+    }
 
-        return false;
+    fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id].parent
     }
 
-    pub fn get_subdir(&mut self, name: &str) -> Option<DirectoryRef> {
+    fn is_dir(&self, id: NodeId) -> bool {
+        self.nodes[id].kind == Kind::Dir
+    }
 
-        for dir in self.subdirs.iter() {
-            if &dir.try_borrow().unwrap().name == name {
-                return Some(dir.clone());
-            }
-        }
-        return None;
+    /// Size of a file, or total size of the files below a directory
+    fn size(&self, id: NodeId) -> u64 {
+        self.nodes[id].size
+    }
+
+    /// Number of directories the node is in, 0 for the root
+    fn depth(&self, id: NodeId) -> usize {
+        std::iter::successors(self.parent(id), |&p| self.parent(p)).count()
     }
 
-    pub fn calculate_sizes(&mut self) {
-        self.size = 0;
+    fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        self.nodes[dir]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.nodes[c].name == name)
+    }
 
-        for dir in self.subdirs.iter() {
-            dir.borrow_mut().calculate_sizes();
-            self.size += dir.try_borrow().unwrap().size;
+    fn add(&mut self, dir: NodeId, name: &str, kind: Kind, size: u64) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent: Some(dir),
+            kind,
+            children: vec![],
+            size,
+        });
+        self.nodes[dir].children.push(id);
+
+        let mut ancestor = Some(dir);
+        while let Some(a) = ancestor {
+            self.nodes[a].size += size;
+            ancestor = self.parent(a);
         }
+        id
+    }
 
-        for f in self.files.iter() {
-            self.size += f.size;
+    /// The subdirectory `name` of `dir`, created if needed
+    fn add_dir(&mut self, dir: NodeId, name: &str) -> NodeId {
+        match self.child(dir, name) {
+            Some(id) => id,
+            None => self.add(dir, name, Kind::Dir, 0),
         }
+    }
 
-        // println!("Dir name: '{0}'\tSize: {1}", self.name, self.size);
+    fn add_file(&mut self, dir: NodeId, name: &str, size: u64) -> NodeId {
+        self.add(dir, name, Kind::File, size)
     }
 
-    #[allow(dead_code)]
-    pub fn print_tree(&self, level: Option<u32>) {
-        let lvlprint = |lvl| {
-            for _ in 0..lvl {
-                print!(" ");
-            }
+    /// Detaches the node, and everything below it, from the tree. It stays in
+    /// the arena but can't be reached from the root anymore.
+    fn remove(&mut self, id: NodeId) {
+        let Some(parent) = self.parent(id) else {
+            panic!("Can't remove the root");
         };
+        self.nodes[parent].children.retain(|&c| c != id);
 
-        let lvl = level.unwrap_or(0);
+        let size = self.size(id);
+        let mut ancestor = Some(parent);
+        while let Some(a) = ancestor {
+            self.nodes[a].size -= size;
+            ancestor = self.parent(a);
+        }
+    }
 
-        lvlprint(lvl);
+    /// Whether `id` is `ancestor` or below it
+    fn is_within(&self, id: NodeId, ancestor: NodeId) -> bool {
+        std::iter::successors(Some(id), |&n| self.parent(n)).any(|n| n == ancestor)
+    }
 
-        if lvl == 0 {
-            println!("- {0} (dir)", self.name);
-        }
+    /// Node at an absolute path like `/a/e`
+    fn lookup(&self, path: &str) -> Option<NodeId> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(ROOT, |dir, name| self.child(dir, name))
+    }
 
-        for dir in self.subdirs.iter() {
-            lvlprint(lvl+1);
-            println!("- {0} (dir)", &dir.try_borrow().unwrap().name);
-            dir.try_borrow().unwrap().print_tree(Some(lvl+1));
-        }
-        
-        
-        for f in self.files.iter() {
-            lvlprint(lvl+1);
-            println!("- {0} (file, size={1})", f.name, f.size);
-        }
+    fn path(&self, id: NodeId) -> String {
+        let mut names: Vec<&str> = std::iter::successors(Some(id), |&n| self.parent(n))
+            .map(|n| self.name(n))
+            .collect();
+        names.pop();
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
 
+    /// `du`: total size below the path
+    fn du(&self, path: &str) -> Option<u64> {
+        self.lookup(path).map(|id| self.size(id))
     }
 
-    pub fn get_dirs_le_than(self_: DirectoryRef, size: u32, out: &mut Vec<DirectoryRef>) {
-        if self_.try_borrow().unwrap().size <= size {
-            out.push(self_.clone());
-        }
-        
-        for dir in self_.try_borrow().unwrap().subdirs.iter() {
-            Self::get_dirs_le_than(dir.clone(), size, out);
+    /// Every node below `id`, parents first and siblings sorted by name
+    fn walk(&self, id: NodeId) -> Vec<NodeId> {
+        let mut ret = vec![];
+        let mut stack = vec![id];
+        while let Some(node) = stack.pop() {
+            ret.push(node);
+            let mut children = self.nodes[node].children.clone();
+            children.sort_by(|&a, &b| self.nodes[b].name.cmp(&self.nodes[a].name));
+            stack.extend(children);
         }
+        ret
     }
 
-    pub fn get_dirs_ge_than(self_: DirectoryRef, size: u32, out: &mut Vec<DirectoryRef>) {
-        if self_.try_borrow().unwrap().size >= size {
-            out.push(self_.clone());
+    fn files(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.walk(ROOT).into_iter().filter(|&id| !self.is_dir(id))
+    }
+
+    fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.walk(ROOT).into_iter().filter(|&id| self.is_dir(id))
+    }
+
+    /// Nodes matching every predicate of the query, in `walk` order
+    fn find(&self, query: &Query) -> Vec<NodeId> {
+        self.walk(ROOT)
+            .into_iter()
+            .filter(|&id| query.matches(self, id))
+            .collect()
+    }
+}
+
+impl fmt::Display for FileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for id in self.walk(ROOT) {
+            let indent = "  ".repeat(self.depth(id));
+            match self.nodes[id].kind {
+                Kind::Dir => writeln!(f, "{indent}- {} (dir)", self.name(id))?,
+                Kind::File => writeln!(
+                    f,
+                    "{indent}- {} (file, size={})",
+                    self.name(id),
+                    self.size(id)
+                )?,
+            }
         }
+        Ok(())
+    }
+}
 
-        for dir in self_.try_borrow().unwrap().subdirs.iter() {
-            Self::get_dirs_ge_than(dir.clone(), size, out);
+/// Whether `name` matches a glob with `*` (any characters) and `?` (one)
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Last `*` seen, and where in the name it started matching
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the `*` match one more character
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp + 1;
+                    n = sn + 1;
+                }
+                None => return false,
+            },
         }
     }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
-fn create_filesystem_tree(terminal_output: &Vec<String>) -> DirectoryRef {
-    let root = Rc::new(RefCell::new(Directory::new_root()));
+/// Predicates for `FileSystem::find`, all of them optional
+#[derive(Debug, Clone)]
+struct Query {
+    name: Option<String>,
+    kind: Option<Kind>,
+    size: (Bound<u64>, Bound<u64>),
+    depth: (Bound<usize>, Bound<usize>),
+}
 
-    let mut current_head: DirectoryRef = root.clone();
+impl Query {
+    fn new() -> Self {
+        Self {
+            name: None,
+            kind: None,
+            size: (Bound::Unbounded, Bound::Unbounded),
+            depth: (Bound::Unbounded, Bound::Unbounded),
+        }
+    }
 
-    for line in terminal_output.iter() {
-        // println!("{}", line);
-        let mut line_iter = line.split(' ');
+    /// Name matching a glob
+    fn name(mut self, glob: &str) -> Self {
+        self.name = Some(glob.to_string());
+        self
+    }
 
-        let first_token = line_iter.next().unwrap();
-        if first_token == "$" {
-            let command = line_iter.next().unwrap();
-            
-            if command == "cd" {
-                let dirname = line_iter.next().unwrap();
-                if dirname == "/" { 
-                    current_head = root.clone();
-                    continue;
-                }
+    fn dirs(mut self) -> Self {
+        self.kind = Some(Kind::Dir);
+        self
+    }
 
-                if dirname == ".." {
-                    let parent_clone = current_head.try_borrow().unwrap().parent.as_ref().unwrap().clone();
-                    current_head = parent_clone;
-                    continue;
-                }
+    fn files(mut self) -> Self {
+        self.kind = Some(Kind::File);
+        self
+    }
 
-                let child = current_head.try_borrow_mut().unwrap()
-                    .get_subdir(dirname).unwrap().clone();
+    fn size(mut self, range: impl RangeBounds<u64>) -> Self {
+        self.size = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// Depth below the root, which is at depth 0
+    fn depth(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.depth = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    fn matches(&self, fs: &FileSystem, id: NodeId) -> bool {
+        self.name
+            .as_ref()
+            .is_none_or(|glob| glob_match(glob, fs.name(id)))
+            && self.kind.is_none_or(|kind| fs.nodes[id].kind == kind)
+            && self.size.contains(&fs.size(id))
+            && self.depth.contains(&fs.depth(id))
+    }
+}
 
-                current_head = child.clone();
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    UnknownCommand {
+        line: usize,
+        command: String,
+    },
+    UnexpectedOutput {
+        line: usize,
+    },
+    InvalidEntry {
+        line: usize,
+        text: String,
+    },
+    NoSuchEntry {
+        line: usize,
+        path: String,
+    },
+    NotADirectory {
+        line: usize,
+        path: String,
+    },
+    NoPreviousDirectory {
+        line: usize,
+    },
+    RemovesCurrentDirectory {
+        line: usize,
+        path: String,
+    },
+    KindMismatch {
+        line: usize,
+        path: String,
+    },
+    SizeMismatch {
+        line: usize,
+        path: String,
+        size: u64,
+        listed: u64,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnknownCommand { line, command } => {
+                write!(f, "Unknown command `{command}` on line {line}")
+            }
+            ParseError::UnexpectedOutput { line } => {
+                write!(f, "Output on line {line} doesn't follow an `ls`")
+            }
+            ParseError::InvalidEntry { line, text } => write!(
+                f,
+                "Invalid entry `{text}` on line {line}, expected `dir NAME` or `SIZE NAME`"
+            ),
+            ParseError::NoSuchEntry { line, path } => {
+                write!(f, "No such file or directory `{path}` on line {line}")
+            }
+            ParseError::NotADirectory { line, path } => {
+                write!(f, "`{path}` on line {line} is not a directory")
             }
+            ParseError::NoPreviousDirectory { line } => {
+                write!(f, "`cd -` on line {line} before any other `cd`")
+            }
+            ParseError::RemovesCurrentDirectory { line, path } => write!(
+                f,
+                "Can't remove `{path}` on line {line}, the current directory is in it"
+            ),
+            ParseError::KindMismatch { line, path } => write!(
+                f,
+                "`{path}` on line {line} was listed both as a file and a directory"
+            ),
+            ParseError::SizeMismatch {
+                line,
+                path,
+                size,
+                listed,
+            } => write!(
+                f,
+                "`{path}` on line {line} is listed with size {listed}, but was {size} before"
+            ),
+        }
+    }
+}
+
+/// Replays a terminal log of `cd`, `ls`, `mkdir` and `rm` commands.
+/// Directories are created when first seen, also by `cd`, and listing a
+/// directory again only adds what is new.
+fn parse_log(terminal_output: &[String]) -> Result<FileSystem, ParseError> {
+    let mut fs = FileSystem::new();
+    let mut cwd = ROOT;
+    let mut previous: Option<NodeId> = None;
+    // Whether the lines are the output of an `ls` of the current directory
+    let mut listing = false;
+
+    for (idx, text) in terminal_output.iter().enumerate() {
+        let line = idx + 1;
+        if text.trim().is_empty() {
             continue;
         }
 
-        if first_token == "dir" {
-            let dirname = line_iter.next().unwrap();
-            
-            if !current_head.try_borrow().unwrap().is_subdir(dirname) {
-                let new_dir = 
-                    Rc::new(
-                        RefCell::new(
-                            Directory::new(
-                                String::from(dirname),
-                                Some(current_head.clone())
-                            )
-                        )
-                    );
-
-                current_head.try_borrow_mut().unwrap().subdirs.push(new_dir);
+        if let Some(command) = text.strip_prefix("$ ") {
+            listing = false;
+            let (command, arg) = command
+                .trim()
+                .split_once(' ')
+                .map_or((command.trim(), ""), |(c, a)| (c, a.trim()));
+            match (command, arg) {
+                ("ls", "") => listing = true,
+                ("cd", "-") => {
+                    let dir = previous.ok_or(ParseError::NoPreviousDirectory { line })?;
+                    previous = Some(cwd);
+                    cwd = dir;
+                }
+                ("cd", path) if !path.is_empty() => {
+                    let dir = resolve(&mut fs, cwd, path, true, line)?;
+                    previous = Some(cwd);
+                    cwd = dir;
+                }
+                ("mkdir", path) if !path.is_empty() => {
+                    resolve(&mut fs, cwd, path, true, line)?;
+                }
+                ("rm", path) if !path.is_empty() => {
+                    let node = resolve(&mut fs, cwd, path, false, line)?;
+                    if fs.is_within(cwd, node) {
+                        return Err(ParseError::RemovesCurrentDirectory {
+                            line,
+                            path: path.to_string(),
+                        });
+                    }
+                    fs.remove(node);
+                    if previous.is_some_and(|p| fs.is_within(p, node)) {
+                        previous = None;
+                    }
+                }
+                _ => {
+                    return Err(ParseError::UnknownCommand {
+                        line,
+                        command: command.to_string(),
+                    })
+                }
             }
             continue;
         }
-        
-        let size = u32::from_str_radix(first_token, 10).unwrap();
-        let filename = line_iter.next().unwrap();
-        current_head.try_borrow_mut().unwrap().files.push(
-            File {
-                name: String::from(filename),
-                size: size
+
+        if !listing {
+            return Err(ParseError::UnexpectedOutput { line });
+        }
+        let invalid = || ParseError::InvalidEntry {
+            line,
+            text: text.to_string(),
+        };
+        let (info, name) = text.split_once(' ').ok_or_else(invalid)?;
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            return Err(invalid());
+        }
+        let path = || format!("{}/{name}", fs.path(cwd).trim_end_matches('/'));
+        match (info, fs.child(cwd, name)) {
+            ("dir", None) => {
+                fs.add_dir(cwd, name);
             }
-        );
+            ("dir", Some(node)) if !fs.is_dir(node) => {
+                return Err(ParseError::KindMismatch { line, path: path() })
+            }
+            ("dir", Some(_)) => (),
+            (size, node) => {
+                let listed: u64 = size.parse().map_err(|_| invalid())?;
+                match node {
+                    None => {
+                        fs.add_file(cwd, name, listed);
+                    }
+                    Some(node) if fs.is_dir(node) => {
+                        return Err(ParseError::KindMismatch { line, path: path() })
+                    }
+                    Some(node) if fs.size(node) != listed => {
+                        return Err(ParseError::SizeMismatch {
+                            line,
+                            path: path(),
+                            size: fs.size(node),
+                            listed,
+                        })
+                    }
+                    Some(_) => (),
+                }
+            }
+        }
     }
-    return root;
+    Ok(fs)
 }
 
+/// Node at an absolute or relative path, with `.` and `..`. With `create`,
+/// missing directories along the way are made.
+fn resolve(
+    fs: &mut FileSystem,
+    cwd: NodeId,
+    path: &str,
+    create: bool,
+    line: usize,
+) -> Result<NodeId, ParseError> {
+    let mut node = if path.starts_with('/') { ROOT } else { cwd };
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        if !fs.is_dir(node) {
+            return Err(ParseError::NotADirectory {
+                line,
+                path: path.to_string(),
+            });
+        }
+        node = match (name, fs.child(node, name)) {
+            (".", _) => node,
+            // Like a shell, `..` at the root stays there
+            ("..", _) => fs.parent(node).unwrap_or(ROOT),
+            (_, Some(child)) => child,
+            (_, None) if create => fs.add_dir(node, name),
+            (_, None) => {
+                return Err(ParseError::NoSuchEntry {
+                    line,
+                    path: path.to_string(),
+                })
+            }
+        };
+    }
+    if create && !fs.is_dir(node) {
+        return Err(ParseError::NotADirectory {
+            line,
+            path: path.to_string(),
+        });
+    }
+    Ok(node)
+}
 
-fn part1(input: &Vec<String>) {
-    let root = create_filesystem_tree(&input);
-    root.borrow_mut().calculate_sizes();
-    // root.try_borrow().unwrap().print_tree(None);
-    
-    let mut out: Vec<DirectoryRef> = vec![];
+/// Disk holding the file system, in bytes
+#[derive(Debug, Clone, Copy)]
+struct Disk {
+    size: u64,
+    /// Unused space wanted
+    target: u64,
+}
 
-    Directory::get_dirs_le_than(root.clone(), 100000, &mut out);
+impl Default for Disk {
+    fn default() -> Self {
+        Self {
+            size: 70_000_000,
+            target: 30_000_000,
+        }
+    }
+}
+
+impl Disk {
+    /// Bytes to delete from the file system to reach the target
+    fn space_to_free(&self, fs: &FileSystem) -> u64 {
+        let unused = self.size.saturating_sub(fs.size(ROOT));
+        self.target.saturating_sub(unused)
+    }
+}
+
+/// Directories to delete, none of them inside another
+#[derive(Debug, Clone, PartialEq)]
+struct Plan {
+    dirs: Vec<NodeId>,
+    freed: u64,
+}
+
+/// Searches for the cleanup plans freeing enough space with at most
+/// `max_deletions` directories, keeping the `count` best ones. Plans are
+/// ranked by bytes freed then number of deletions, and only minimal plans are
+/// kept: every directory in them is needed to reach the target.
+struct Planner<'a> {
+    fs: &'a FileSystem,
+    to_free: u64,
+    max_deletions: usize,
+    count: usize,
+    /// Directories in `walk` order, so the ones below a directory follow it
+    dirs: Vec<NodeId>,
+    /// Index in `dirs` after the last directory below each one
+    ends: Vec<usize>,
+    best: Vec<Plan>,
+}
+
+impl<'a> Planner<'a> {
+    fn new(fs: &'a FileSystem, disk: Disk, max_deletions: usize, count: usize) -> Self {
+        let dirs: Vec<NodeId> = fs.dirs().collect();
+        let ends = (0..dirs.len())
+            .map(|i| {
+                (i + 1..dirs.len())
+                    .find(|&j| !fs.is_within(dirs[j], dirs[i]))
+                    .unwrap_or(dirs.len())
+            })
+            .collect();
+        Self {
+            fs,
+            to_free: disk.space_to_free(fs),
+            max_deletions,
+            count,
+            dirs,
+            ends,
+            best: vec![],
+        }
+    }
+
+    fn plans(mut self) -> Vec<Plan> {
+        self.search(0, &mut vec![], 0);
+        self.best
+    }
+
+    fn search(&mut self, start: usize, chosen: &mut Vec<NodeId>, freed: u64) {
+        if freed >= self.to_free {
+            self.record(chosen, freed);
+            return;
+        }
+        // Plans only free more as directories are added
+        let full = self.best.len() == self.count;
+        if chosen.len() == self.max_deletions || full && freed > self.best[self.count - 1].freed {
+            return;
+        }
+
+        for i in start..self.dirs.len() {
+            let dir = self.dirs[i];
+            chosen.push(dir);
+            self.search(self.ends[i], chosen, freed + self.fs.size(dir));
+            chosen.pop();
+        }
+    }
 
-    let mut acc = 0u32;
-    for dir in out.iter() {
-        acc += dir.try_borrow().unwrap().size;
+    fn record(&mut self, chosen: &[NodeId], freed: u64) {
+        let smallest = chosen.iter().map(|&d| self.fs.size(d)).min().unwrap_or(0);
+        if !chosen.is_empty() && freed - smallest >= self.to_free {
+            return;
+        }
+
+        let plan = Plan {
+            dirs: chosen.to_vec(),
+            freed,
+        };
+        let rank = |p: &Plan| (p.freed, p.dirs.len());
+        let idx = self.best.partition_point(|p| rank(p) <= rank(&plan));
+        if idx < self.count {
+            self.best.insert(idx, plan);
+            self.best.truncate(self.count);
+        }
     }
-    println!("{}", acc);
 }
 
-fn part2(input: &Vec<String>) {
-    let root = create_filesystem_tree(&input);
-    root.borrow_mut().calculate_sizes();
-    // root.try_borrow().unwrap().print_tree(None);
-    
-    const TOTAL_SPACE: u32 = 70_000_000;
-    const MIN_UNUSED_SPACE: u32 = 30_000_000;
+/// Prints the `count` best plans with at most `max_deletions` directories
+fn print_plans(fs: &FileSystem, disk: Disk, max_deletions: usize, count: usize) {
+    println!(
+        "\nCleanup plans to free {} bytes, deleting up to {max_deletions} directories:",
+        disk.space_to_free(fs)
+    );
+    let plans = Planner::new(fs, disk, max_deletions, count).plans();
+    if plans.is_empty() {
+        println!("none");
+    }
+    for plan in plans {
+        let paths: Vec<String> = plan.dirs.iter().map(|&d| fs.path(d)).collect();
+        println!("{}\t{}", plan.freed, paths.join(" "));
+    }
+}
 
-    let used_space = root.try_borrow().unwrap().size;
-    let min_space_to_free = MIN_UNUSED_SPACE - (TOTAL_SPACE - used_space);
+fn part1(fs: &FileSystem) {
+    let small_dirs = fs.find(&Query::new().dirs().size(..=100_000));
+    println!("{}", small_dirs.iter().map(|&d| fs.size(d)).sum::<u64>());
+}
 
-    let mut out: Vec<DirectoryRef> = vec![];
+fn part2(fs: &FileSystem, disk: Disk) {
+    // The smallest directory that is big enough
+    match Planner::new(fs, disk, 1, 1).plans().first() {
+        Some(plan) => println!("{}", plan.freed),
+        None => eprintln!("Can't free {} bytes", disk.space_to_free(fs)),
+    }
+}
 
-    Directory::get_dirs_ge_than(root.clone(), min_space_to_free, &mut out);
+/// Handles `--tree`, `--files`, `--du=PATH` and `--find[=GLOB]` with
+/// `--type=f|d`, `--min-size=N`, `--max-size=N` and `--max-depth=N`
+fn explore(fs: &FileSystem) {
+    if has_flag("--tree") {
+        print!("\n{fs}");
+    }
+    if has_flag("--files") {
+        println!();
+        for id in fs.files() {
+            println!("{}\t{}", fs.size(id), fs.path(id));
+        }
+    }
+    if let Some(path) = flag_value("--du") {
+        match fs.du(&path) {
+            Some(size) => println!("\n{size}\t{path}"),
+            None => eprintln!("\nNo such file or directory: {path}"),
+        }
+    }
 
-    let mut smallest_possible_dir: DirectoryRef = root.clone();
-    for dir in out.iter() {
-        // println!("{:?}", dir.try_borrow().unwrap().name);
-        if dir.try_borrow().unwrap().size < smallest_possible_dir.try_borrow().unwrap().size {
-            smallest_possible_dir = dir.clone();
+    let glob = flag_value("--find");
+    if glob.is_none() && !has_flag("--find") {
+        return;
+    }
+    let query = match find_query(glob) {
+        Ok(query) => query,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
         }
+    };
+
+    println!();
+    for id in fs.find(&query) {
+        println!("{}\t{}", fs.size(id), fs.path(id));
+    }
+}
+
+/// Query of `--find[=GLOB]` and the flags that narrow it down
+fn find_query(glob: Option<String>) -> Result<Query, String> {
+    let mut query = Query::new()
+        .size((
+            number_flag("--min-size")?.map_or(Bound::Unbounded, Bound::Included),
+            number_flag("--max-size")?.map_or(Bound::Unbounded, Bound::Included),
+        ))
+        .depth(..=number_flag("--max-depth")?.map_or(usize::MAX, |d| d as usize));
+    if let Some(glob) = glob {
+        query = query.name(&glob);
+    }
+    match flag_value("--type").as_deref() {
+        None => Ok(query),
+        Some("f") => Ok(query.files()),
+        Some("d") => Ok(query.dirs()),
+        Some(_) => Err("`--type` expects `f` for files or `d` for directories".to_string()),
     }
-    println!("{}", smallest_possible_dir.try_borrow().unwrap().size);
+}
+
+/// Disk given by `--disk=N` and `--target=N`, with the maximum number of
+/// deletions and of plans given by `--deletions=N` and `--plans=N`
+fn disk_flags() -> Result<(Disk, Option<(u64, u64)>), String> {
+    let default = Disk::default();
+    let disk = Disk {
+        size: number_flag("--disk")?.unwrap_or(default.size),
+        target: number_flag("--target")?.unwrap_or(default.target),
+    };
+    let plans = match number_flag("--deletions")? {
+        Some(max_deletions) => Some((max_deletions, number_flag("--plans")?.unwrap_or(10))),
+        None => None,
+    };
+    Ok((disk, plans))
+}
+
+/// Value of a flag taking a number
+fn number_flag(flag: &str) -> Result<Option<u64>, String> {
+    flag_value(flag)
+        .map(|n| n.parse().map_err(|_| format!("`{flag}` expects a number")))
+        .transpose()
 }
 
 fn main() {
@@ -238,10 +732,213 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: Vec<String> = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    let fs = match parse_log(&input) {
+        Ok(fs) => fs,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    let (disk, plans) = match disk_flags() {
+        Ok(flags) => flags,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    run_part("PART 1", || part1(&fs));
+    run_part("PART 2", || part2(&fs, disk));
+
+    explore(&fs);
+    if let Some((max_deletions, count)) = plans {
+        print_plans(&fs, disk, max_deletions as usize, count as usize);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn example() -> FileSystem {
+        let input: Vec<String> = std::fs::read_to_string("data/examples/07.txt")
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        parse_log(&input).unwrap()
+    }
+
+    #[test]
+    fn paths() {
+        let fs = example();
+        let e = fs.lookup("/a/e").unwrap();
+        assert_eq!(fs.path(e), "/a/e");
+        assert_eq!(fs.depth(e), 2);
+        assert_eq!(fs.lookup("/"), Some(ROOT));
+        assert_eq!(fs.lookup("/a/x"), None);
+        assert_eq!(fs.du("/a/e"), Some(584));
+        assert_eq!(fs.du("/a"), Some(94853));
+        assert_eq!(fs.du("/d"), Some(24933642));
+        assert_eq!(fs.du("/"), Some(48381165));
+        assert_eq!(fs.du("/d/j"), Some(4060174));
+        assert_eq!(fs.files().count(), 10);
+        assert_eq!(fs.files().map(|f| fs.size(f)).sum::<u64>(), fs.size(ROOT));
+    }
+
+    #[test]
+    fn queries() {
+        let fs = example();
+        let find = |query: Query| -> Vec<String> {
+            fs.find(&query).into_iter().map(|id| fs.path(id)).collect()
+        };
+        assert_eq!(find(Query::new().dirs()), ["/", "/a", "/a/e", "/d"]);
+        assert_eq!(find(Query::new().dirs().size(..=100_000)), ["/a", "/a/e"]);
+        assert_eq!(
+            find(Query::new().name("*.*")),
+            ["/a/h.lst", "/b.txt", "/c.dat", "/d/d.ext", "/d/d.log"]
+        );
+        assert_eq!(
+            find(Query::new().name("?").depth(2..)),
+            ["/a/e", "/a/e/i", "/a/f", "/a/g", "/d/j", "/d/k"]
+        );
+        assert_eq!(
+            find(Query::new().files().size(5_000_000..8_000_000).depth(2..=2)),
+            ["/d/d.ext", "/d/k"]
+        );
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*.txt", "b.txt"));
+        assert!(!glob_match("*.txt", "b.txt.gz"));
+        assert!(glob_match("d*.*", "d.log"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("?.dat", "c.dat"));
+        assert!(!glob_match("?", ""));
+    }
+
+    fn log(text: &str) -> Result<FileSystem, ParseError> {
+        let input: Vec<String> = text.lines().map(|ln| ln.trim().to_string()).collect();
+        parse_log(&input)
+    }
+
+    #[test]
+    fn shell_commands() {
+        let fs = log("
+            $ cd /a/b
+            $ ls
+            10 x
+            $ cd ..
+            $ ls
+            dir b
+            dir c
+            20 y
+            $ ls
+            dir b
+            20 y
+            $ cd -
+            $ cd ../../../a/./c
+            $ mkdir d/e
+            $ cd d/e
+            $ ls
+            30 z
+            $ rm /a/b
+            $ cd /
+            $ ls
+            dir a
+            5 w
+        ")
+        .unwrap();
+        assert_eq!(fs.du("/"), Some(55));
+        assert_eq!(fs.du("/a"), Some(50));
+        assert_eq!(fs.du("/a/b"), None);
+        assert_eq!(fs.du("/a/c/d/e/z"), Some(30));
+        let files: Vec<String> = fs.files().map(|f| fs.path(f)).collect();
+        assert_eq!(files, ["/a/c/d/e/z", "/a/y", "/w"]);
+    }
+
+    #[test]
+    fn log_errors() {
+        let error = |text: &str| log(text).err().unwrap().to_string();
+        assert_eq!(error("$ cd /\n$ cp a b"), "Unknown command `cp` on line 2");
+        assert_eq!(
+            error("$ cd a\n10 x"),
+            "Output on line 2 doesn't follow an `ls`"
+        );
+        assert_eq!(
+            error("$ ls\nten x"),
+            "Invalid entry `ten x` on line 2, expected `dir NAME` or `SIZE NAME`"
+        );
+        assert_eq!(error("$ rm a"), "No such file or directory `a` on line 1");
+        assert_eq!(
+            error("$ ls\n10 x\n$ cd x"),
+            "`x` on line 3 is not a directory"
+        );
+        assert_eq!(error("$ cd -"), "`cd -` on line 1 before any other `cd`");
+        assert_eq!(
+            error("$ cd /a/b\n$ rm /a"),
+            "Can't remove `/a` on line 2, the current directory is in it"
+        );
+        assert_eq!(
+            error("$ ls\ndir x\n$ ls\n10 x"),
+            "`/x` on line 4 was listed both as a file and a directory"
+        );
+        assert_eq!(
+            error("$ cd a\n$ ls\n10 x\n$ ls\n20 x"),
+            "`/a/x` on line 5 is listed with size 20, but was 10 before"
+        );
+    }
+
+    #[test]
+    fn cleanup_plans() {
+        let fs = example();
+        let disk = Disk::default();
+        assert_eq!(disk.space_to_free(&fs), 8381165);
+        let plans = |max_deletions, count| -> Vec<(u64, Vec<String>)> {
+            Planner::new(&fs, disk, max_deletions, count)
+                .plans()
+                .into_iter()
+                .map(|p| (p.freed, p.dirs.iter().map(|&d| fs.path(d)).collect()))
+                .collect()
+        };
+        let plan = |freed, dirs: &[&str]| (freed, dirs.iter().map(|d| d.to_string()).collect());
+        assert_eq!(
+            plans(1, 5),
+            [plan(24933642, &["/d"]), plan(48381165, &["/"])]
+        );
+
+        // Smaller disk, with 24933642 + 94853 bytes to free
+        let disk = Disk {
+            size: 48381165,
+            target: 25028495,
+        };
+        let plans = |max_deletions| -> Vec<u64> {
+            Planner::new(&fs, disk, max_deletions, 5)
+                .plans()
+                .iter()
+                .map(|p| p.freed)
+                .collect()
+        };
+        assert_eq!(plans(1), [48381165]);
+        assert_eq!(plans(2), [25028495, 48381165]);
+        // `/a/e` and `/a` together would be nested, and `/a` alone is enough
+        assert_eq!(plans(3), [25028495, 48381165]);
+
+        let disk = Disk {
+            size: 100_000_000,
+            target: 10,
+        };
+        assert_eq!(
+            Planner::new(&fs, disk, 2, 5).plans(),
+            [Plan {
+                dirs: vec![],
+                freed: 0
+            }]
+        );
+    }
 }
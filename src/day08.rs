@@ -1,167 +1,563 @@
-use aoc::read_inputs;
+use aoc::{flag_value, has_flag, input_arg, read_inputs, run_part};
+use std::fmt;
 
 type GridT = Vec<Vec<u32>>;
 
+/// A value for every tree of the grid
+type Map<T> = Vec<Vec<T>>;
 
-#[allow(dead_code)]
-fn print_grid(grid: &GridT) {
-    let nrow = grid.len();
-    let ncol = grid[0].len();
-    
-    for row in 0..nrow {
-        for col in 0..ncol {
-            print!("{} ", grid[row][col]);
+/// Row and column steps towards the left, right, top and bottom edges
+const DIRECTIONS: [(isize, isize); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+/// `DIRECTIONS` and the diagonals
+const DIRECTIONS_8: [(isize, isize); 8] = [
+    (0, -1),
+    (0, 1),
+    (-1, 0),
+    (1, 0),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+];
+
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    Empty,
+    InvalidHeight {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    RaggedRow {
+        line: usize,
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "There are no trees"),
+            ParseError::InvalidHeight {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "Invalid height `{found}` on line {line}, column {column}, expected a digit"
+            ),
+            ParseError::RaggedRow {
+                line,
+                len,
+                expected,
+            } => write!(
+                f,
+                "Line {line} has {len} trees, but the first one has {expected}"
+            ),
         }
-        print!("\n");
     }
 }
 
+fn parse_grid(input: &[String]) -> Result<GridT, ParseError> {
+    let grid: GridT = input
+        .iter()
+        .enumerate()
+        .map(|(i, ln)| {
+            ln.chars()
+                .enumerate()
+                .map(|(j, c)| {
+                    c.to_digit(10).ok_or(ParseError::InvalidHeight {
+                        line: i + 1,
+                        column: j + 1,
+                        found: c,
+                    })
+                })
+                .collect()
+        })
+        .collect::<Result<_, _>>()?;
 
-fn is_visible(grid: &GridT, row: usize, col: usize) -> bool {
-    let nrow = grid.len();
-    let ncol = grid[0].len();
-    let tree_h = grid[row][col];
-
-    let mut is_tallest = true;
-    // check left
-    for i in 0..row {
-        is_tallest = is_tallest && (grid[i][col] < tree_h);
+    let expected = grid.first().map_or(0, |row| row.len());
+    if expected == 0 {
+        return Err(ParseError::Empty);
     }
-    if is_tallest {
-        return true;
+    if let Some(i) = grid.iter().position(|row| row.len() != expected) {
+        return Err(ParseError::RaggedRow {
+            line: i + 1,
+            len: grid[i].len(),
+            expected,
+        });
     }
+    Ok(grid)
+}
 
-    let mut is_tallest = true;
-    // check right
-    for i in row+1..nrow {
-        is_tallest = is_tallest && (grid[i][col] < tree_h);
-    }
-    if is_tallest {
-        return true;
-    }
+/// Lines of trees going away from the edge in `dir`, each starting at the edge
+fn lines_from(nrow: usize, ncol: usize, (dr, dc): (isize, isize)) -> Vec<Vec<(usize, usize)>> {
+    let step = |(row, col): (usize, usize), (dr, dc): (isize, isize)| {
+        let (row, col) = (row.checked_add_signed(dr)?, col.checked_add_signed(dc)?);
+        (row < nrow && col < ncol).then_some((row, col))
+    };
 
-    let mut is_tallest = true;
-    // check top
-    for i in 0..col {
-        is_tallest = is_tallest && (grid[row][i] < tree_h);
-    }
-    if is_tallest {
-        return true;
-    }
+    (0..nrow)
+        .flat_map(|row| (0..ncol).map(move |col| (row, col)))
+        .filter(|&pos| step(pos, (dr, dc)).is_none())
+        .map(|start| std::iter::successors(Some(start), |&pos| step(pos, (-dr, -dc))).collect())
+        .collect()
+}
 
-    let mut is_tallest = true;
-    // check left
-    for i in col+1..ncol {
-        is_tallest = is_tallest && (grid[row][i] < tree_h);
-    }
-    if is_tallest {
-        return true;
+/// Where the trees are looked from, and in which directions
+#[derive(Debug, Clone, Copy)]
+struct View {
+    /// Row and column steps of the directions looked at
+    directions: &'static [(isize, isize)],
+    /// Height of the viewers' eyes above the top of the tree they stand on.
+    /// Trees only block the view if they reach that height.
+    eye: u32,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            directions: &DIRECTIONS,
+            eye: 0,
+        }
     }
+}
 
-    false
+/// Visibility from outside the grid and viewing distances of every tree
+struct Visibility {
+    /// Whether the tree can be seen from any edge in the view's directions
+    visible: Map<bool>,
+    /// Trees seen from the tree towards each of the view's directions, up to
+    /// the first one blocking the view or the edge
+    distances: Map<Vec<usize>>,
 }
 
+impl Visibility {
+    /// Looks at every line of trees once from each edge, keeping a stack of
+    /// the trees that may still block the view of the next ones. Heights are
+    /// digits and strictly decrease along the stack, so it never holds more
+    /// than 10 trees and every line takes linear time.
+    fn new(grid: &GridT, view: &View) -> Self {
+        let (nrow, ncol) = (grid.len(), grid[0].len());
+        let mut visible = vec![vec![false; ncol]; nrow];
+        let mut distances = vec![vec![vec![0; view.directions.len()]; ncol]; nrow];
 
-fn get_scenic_score(grid: &GridT, row: usize, col: usize) -> u32 {
-    let nrow = grid.len();
-    let ncol = grid[0].len();
-    let tree_h = grid[row][col];
+        for (d, &dir) in view.directions.iter().enumerate() {
+            for line in lines_from(nrow, ncol, dir) {
+                let height = |i: usize| grid[line[i].0][line[i].1];
+                // Indices in the line of trees with decreasing heights, the
+                // others being hidden behind a closer tree at least as tall
+                let mut stack: Vec<usize> = vec![];
+                for (i, &(row, col)) in line.iter().enumerate() {
+                    // The closest tree tall enough to block the view
+                    let blocking = stack
+                        .iter()
+                        .rev()
+                        .find(|&&j| height(j) >= height(i) + view.eye);
+                    match blocking {
+                        Some(&j) => distances[row][col][d] = i - j,
+                        None => {
+                            distances[row][col][d] = i;
+                            visible[row][col] = true;
+                        }
+                    }
 
-    let mut total_score = 1;
-    
-    // look left
-    let mut current_score = 0;
-    for i in (0..row).rev() {
-        current_score += 1;
-        if grid[i][col] >= tree_h {
-            break;
+                    while stack.last().is_some_and(|&j| height(j) <= height(i)) {
+                        stack.pop();
+                    }
+                    stack.push(i);
+                }
+            }
         }
+        Self { visible, distances }
     }
-    total_score *= current_score;
 
-    // look right
-    let mut current_score = 0;
-    for i in row+1..nrow {
-        current_score += 1;
-        if grid[i][col] >= tree_h {
-            break;
-        }
+    fn count_visible(&self) -> usize {
+        self.visible.iter().flatten().filter(|&&v| v).count()
     }
-    total_score *= current_score;
 
-    // look top
-    let mut current_score = 0;
-    for i in (0..col).rev() {
-        current_score += 1;
-        if grid[row][i] >= tree_h {
-            break;
-        }
+    /// Product of the viewing distances of every tree
+    fn scenic_scores(&self) -> Map<u64> {
+        self.distances
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|d| d.iter().map(|&x| x as u64).product())
+                    .collect()
+            })
+            .collect()
     }
-    total_score *= current_score;
+}
 
-    // look bottom
-    let mut current_score = 0;
-    for i in col+1..ncol {
-        current_score += 1;
-        if grid[row][i] >= tree_h {
-            break;
+/// Cells on the straight line from `from` to `to`, both included
+fn line_between(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    // Bresenham's algorithm
+    let (dr, dc) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let (sr, sc) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let mut err = dr + dc;
+    let mut pos = from;
+    let mut ret = vec![pos];
+    while pos != to {
+        let e2 = 2 * err;
+        if e2 >= dc {
+            err += dc;
+            pos.0 += sr;
+        }
+        if e2 <= dr {
+            err += dr;
+            pos.1 += sc;
         }
+        ret.push(pos);
+    }
+    ret
+}
+
+/// Trees that can be seen from a point outside the grid, at ground level.
+/// Trees in between only block the view if they reach the tree's height plus
+/// `eye`.
+fn visible_from(grid: &GridT, point: (i64, i64), eye: u32) -> Result<Map<bool>, String> {
+    let (nrow, ncol) = (grid.len() as i64, grid[0].len() as i64);
+    let in_grid = |(row, col): (i64, i64)| (0..nrow).contains(&row) && (0..ncol).contains(&col);
+    if in_grid(point) {
+        return Err(format!("{point:?} is not outside the grid"));
     }
-    total_score *= current_score;
 
-    total_score
+    let height = |(row, col): (i64, i64)| grid[row as usize][col as usize];
+    Ok((0..nrow)
+        .map(|row| {
+            (0..ncol)
+                .map(|col| {
+                    let line = line_between(point, (row, col));
+                    line[1..line.len() - 1]
+                        .iter()
+                        .filter(|&&pos| in_grid(pos))
+                        .all(|&pos| height(pos) < height((row, col)) + eye)
+                })
+                .collect()
+        })
+        .collect())
 }
 
+/// Scales the values to `0..=max_level`, the largest value being `max_level`
+fn levels(values: &Map<u64>, max_level: u64) -> Map<u64> {
+    let max = values.iter().flatten().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|row| row.iter().map(|&v| v * max_level / max).collect())
+        .collect()
+}
+
+/// Colour of a level in `0..=255`, from blue to red
+fn heat(level: u64) -> (u8, u8, u8) {
+    let level = level.min(255) as u8;
+    (level, 0, 255 - level)
+}
 
-fn part1(grid: &GridT) {
-    let nrow = grid.len();
-    let ncol = grid[0].len();
-    
-    let mut sum = 2*nrow + 2*(ncol-2);
-    for row in 1..nrow-1 {
-        for col in 1..ncol-1 {
-            // println!("{0}, is_visible={1}",grid[row][col],is_visible(&grid, row, col));
-            if is_visible(&grid, row, col) {
-                sum += 1;
+/// Prints the heights of the trees, over a heatmap of `values` if given
+fn print_grid(grid: &GridT, values: Option<&Map<u64>>) {
+    let levels = values.map(|values| levels(values, 255));
+    for (row, heights) in grid.iter().enumerate() {
+        for (col, height) in heights.iter().enumerate() {
+            match &levels {
+                Some(levels) => {
+                    let (r, g, b) = heat(levels[row][col]);
+                    print!("\x1b[48;2;{r};{g};{b}m{height} \x1b[0m");
+                }
+                None => print!("{height} "),
             }
         }
+        println!();
     }
+}
 
-    println!("{}", sum);
+fn to_csv(values: &Map<u64>) -> String {
+    values
+        .iter()
+        .map(|row| {
+            let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            row.join(",") + "\n"
+        })
+        .collect()
 }
 
-fn part2(grid: &GridT) {
-    let nrow = grid.len();
-    let ncol = grid[0].len();
-    
-    let mut best_score = 0;
-    for row in 1..nrow-1 {
-        for col in 1..ncol-1 {
-            let current_score = get_scenic_score(&grid, row, col);
-            best_score = std::cmp::max(best_score, current_score);
-            // println!("{0} score={1}", grid[row][col], current_score);
+/// Plain grayscale PGM image, one pixel per tree
+fn to_pgm(values: &Map<u64>) -> String {
+    let mut ret = format!("P2\n{} {}\n255\n", values[0].len(), values.len());
+    for row in levels(values, 255) {
+        let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+        ret += &(row.join(" ") + "\n");
+    }
+    ret
+}
+
+/// Plain PPM image of the heatmap, one pixel per tree
+fn to_ppm(values: &Map<u64>) -> String {
+    let mut ret = format!("P3\n{} {}\n255\n", values[0].len(), values.len());
+    for row in levels(values, 255) {
+        let row: Vec<String> = row
+            .iter()
+            .map(|&v| {
+                let (r, g, b) = heat(v);
+                format!("{r} {g} {b}")
+            })
+            .collect();
+        ret += &(row.join(" ") + "\n");
+    }
+    ret
+}
+
+/// Handles `--map=visible|scenic`, `--heatmap` and `--export=FILE` with a
+/// `.csv`, `.pgm` or `.ppm` extension
+fn show_maps(grid: &GridT, view: &View) {
+    let visibility = Visibility::new(grid, view);
+    let values = match flag_value("--map").as_deref() {
+        Some("visible") => visibility
+            .visible
+            .iter()
+            .map(|row| row.iter().map(|&v| v as u64).collect())
+            .collect(),
+        None | Some("scenic") => visibility.scenic_scores(),
+        Some(map) => {
+            eprintln!("Unknown map `{map}`, expected `visible` or `scenic`");
+            return;
+        }
+    };
+
+    if has_flag("--heatmap") {
+        println!();
+        print_grid(grid, Some(&values));
+    }
+    if let Some(path) = flag_value("--export") {
+        let contents = match path.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("csv") => to_csv(&values),
+            Some("pgm") => to_pgm(&values),
+            Some("ppm") => to_ppm(&values),
+            _ => {
+                eprintln!("Can't export to {path}, expected a .csv, .pgm or .ppm file");
+                return;
+            }
+        };
+        if let Err(e) = std::fs::write(&path, contents) {
+            eprintln!("Can't write {path}: {e}");
         }
     }
+}
+
+fn part1(grid: &GridT, view: &View) {
+    println!("{}", Visibility::new(grid, view).count_visible());
+}
 
-    println!("{}", best_score);
+fn part2(grid: &GridT, view: &View) {
+    let scores = Visibility::new(grid, view).scenic_scores();
+    println!("{}", scores.iter().flatten().max().unwrap());
 }
 
 fn main() {
-    let day = 08;
+    let day = 8;
     #[cfg(debug_assertions)]
     let file_path = format!("data/examples/{:02}.txt", day);
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: Vec<String> = read_inputs(&file_path).unwrap();
-    let mut grid: GridT = vec![];
-    for item in input.iter() {
-        grid.push(item.chars().filter_map(|c| c.to_digit(10)).collect())
-    }
-    // print_grid(&grid);
-    println!("PART 1:");
-    part1(&grid);
-    println!("PART 2:");
-    part2(&grid);
+    let grid = match parse_grid(&input) {
+        Ok(grid) => grid,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    if has_flag("--grid") {
+        print_grid(&grid, None);
+    }
+    let (view, from) = match view_flags().and_then(|view| Ok((view, from_flag()?))) {
+        Ok(flags) => flags,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    run_part("PART 1", || part1(&grid, &view));
+    run_part("PART 2", || part2(&grid, &view));
+
+    show_maps(&grid, &view);
+    if let Some(point) = from {
+        match visible_from(&grid, point, view.eye) {
+            Ok(visible) => {
+                let count = visible.iter().flatten().filter(|&&v| v).count();
+                println!("\nTrees visible from {point:?}: {count}");
+            }
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+/// View given by `--directions=4|8` and `--eye=N`
+fn view_flags() -> Result<View, String> {
+    let directions: &[(isize, isize)] = match flag_value("--directions").as_deref() {
+        None | Some("4") => &DIRECTIONS,
+        Some("8") => &DIRECTIONS_8,
+        Some(n) => return Err(format!("`--directions` expects 4 or 8, not {n}")),
+    };
+    let eye = match flag_value("--eye") {
+        None => 0,
+        Some(eye) => eye.parse().map_err(|_| "`--eye` expects a height")?,
+    };
+    Ok(View { directions, eye })
+}
+
+/// Point given by `--from=row,col`
+fn from_flag() -> Result<Option<(i64, i64)>, String> {
+    flag_value("--from")
+        .map(|point| {
+            point
+                .split_once(',')
+                .and_then(|(row, col)| Some((row.parse().ok()?, col.parse().ok()?)))
+                .ok_or_else(|| "`--from` expects a point `row,col`".to_string())
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> GridT {
+        let input: Vec<String> = rows.iter().map(|r| r.to_string()).collect();
+        parse_grid(&input).unwrap()
+    }
+
+    fn example() -> GridT {
+        grid(&["30373", "25512", "65332", "33549", "35390"])
+    }
+
+    #[test]
+    fn visibility_maps() {
+        let vis = Visibility::new(&example(), &View::default());
+        assert_eq!(vis.count_visible(), 21);
+        assert_eq!(vis.visible[1], [true, true, true, false, true]);
+        // Left, right, top, bottom
+        assert_eq!(vis.distances[1][2], [1, 2, 1, 2]);
+        assert_eq!(vis.distances[3][2], [2, 2, 2, 1]);
+        let scores = vis.scenic_scores();
+        assert_eq!(scores[1][2], 4);
+        assert_eq!(scores[3][2], 8);
+        assert_eq!(scores[0], [0; 5]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |rows: &[&str]| {
+            let input: Vec<String> = rows.iter().map(|r| r.to_string()).collect();
+            parse_grid(&input).unwrap_err().to_string()
+        };
+        assert_eq!(
+            error(&["303", "2x5"]),
+            "Invalid height `x` on line 2, column 2, expected a digit"
+        );
+        assert_eq!(
+            error(&["303", "25"]),
+            "Line 2 has 2 trees, but the first one has 3"
+        );
+        assert_eq!(error(&[]), "There are no trees");
+    }
+
+    #[test]
+    fn exports() {
+        let values = vec![vec![0, 4, 8], vec![2, 1, 0]];
+        assert_eq!(to_csv(&values), "0,4,8\n2,1,0\n");
+        assert_eq!(to_pgm(&values), "P2\n3 2\n255\n0 127 255\n63 31 0\n");
+        assert_eq!(
+            to_ppm(&values),
+            "P3\n3 2\n255\n0 0 255 127 0 128 255 0 0\n63 0 192 31 0 224 0 0 255\n"
+        );
+        // All zeros stays zero
+        assert_eq!(levels(&vec![vec![0, 0]], 255), [[0, 0]]);
+    }
+
+    /// Trees seen from `(row, col)` towards `dir`, and whether it is visible
+    /// from that edge, looking at every tree in between
+    fn look(
+        grid: &GridT,
+        (row, col): (usize, usize),
+        dir: (isize, isize),
+        eye: u32,
+    ) -> (usize, bool) {
+        let lines = lines_from(grid.len(), grid[0].len(), dir);
+        let line = lines
+            .iter()
+            .find(|line| line.contains(&(row, col)))
+            .unwrap();
+        let i = line.iter().position(|&pos| pos == (row, col)).unwrap();
+        let tall = |&(r, c): &(usize, usize)| grid[r][c] >= grid[row][col] + eye;
+        match line[..i].iter().rposition(tall) {
+            Some(j) => (i - j, false),
+            None => (i, true),
+        }
+    }
+
+    #[test]
+    fn generalized_views() {
+        let grid = example();
+        for directions in [&DIRECTIONS[..], &DIRECTIONS_8[..]] {
+            for eye in 0..3 {
+                let vis = Visibility::new(&grid, &View { directions, eye });
+                for row in 0..5 {
+                    for col in 0..5 {
+                        let looks: Vec<(usize, bool)> = directions
+                            .iter()
+                            .map(|&dir| look(&grid, (row, col), dir, eye))
+                            .collect();
+                        let distances: Vec<usize> = looks.iter().map(|l| l.0).collect();
+                        assert_eq!(vis.distances[row][col], distances);
+                        assert_eq!(vis.visible[row][col], looks.iter().any(|l| l.1));
+                    }
+                }
+            }
+        }
+
+        let eight = Visibility::new(
+            &grid,
+            &View {
+                directions: &DIRECTIONS_8,
+                eye: 0,
+            },
+        );
+        // The 4 next to the bottom right corner is seen diagonally
+        assert!(eight.visible[3][3]);
+        assert!(!Visibility::new(&grid, &View::default()).visible[3][3]);
+        assert_eq!(eight.count_visible(), 22);
+    }
+
+    #[test]
+    fn external_point() {
+        let grid = example();
+        // Straight above a column is the same as the top edge
+        let from_top = visible_from(&grid, (-1, 3), 0).unwrap();
+        let column: Vec<bool> = from_top.iter().map(|row| row[3]).collect();
+        assert_eq!(column, [true, false, false, false, true]);
+
+        assert_eq!(
+            line_between((-1, -1), (2, 2)),
+            [(-1, -1), (0, 0), (1, 1), (2, 2)]
+        );
+        assert_eq!(
+            line_between((5, 0), (1, 2)),
+            [(5, 0), (4, 1), (3, 1), (2, 2), (1, 2)]
+        );
+        let from_corner = visible_from(&grid, (5, -1), 0).unwrap();
+        // The 3 in the middle is behind the 3s at (4, 0) and (3, 1)
+        assert!(!from_corner[2][2]);
+        // The 5 above it is behind the 5 at (2, 1), unless looking 3 higher
+        assert!(!from_corner[1][2]);
+        assert!(visible_from(&grid, (5, -1), 3).unwrap()[1][2]);
+        assert_eq!(
+            visible_from(&grid, (2, 2), 0),
+            Err("(2, 2) is not outside the grid".to_string())
+        );
+    }
 }
@@ -1,6 +1,9 @@
-use aoc::read_inputs;
+use aoc::sim::{debug, Simulation};
+use aoc::{flag_value, has_flag, input_arg, read_inputs, run_part};
+use std::collections::HashSet;
+use std::fmt;
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 struct Position {
     x: i32,
     y: i32
@@ -15,153 +18,490 @@ impl Position {
     }
 }
 
-fn fix_tail_pos(tail: &mut Position, head: &Position) {
-    let rel_posx = head.x - tail.x;
-    let rel_posy = head.y - tail.y;
-    
-    if tail.y == head.y {
-        if rel_posx > 1 {
-            tail.x += 1
-        } 
-        else if rel_posx < -1 {
-            tail.x -= 1;
-        }
+/// Direction of a step of the head, diagonals included
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dir {
+    R,
+    L,
+    U,
+    D,
+    UR,
+    UL,
+    DR,
+    DL,
+}
+
+impl Dir {
+    fn parse(dir: &str) -> Option<Self> {
+        Some(match dir {
+            "R" => Dir::R,
+            "L" => Dir::L,
+            "U" => Dir::U,
+            "D" => Dir::D,
+            "UR" => Dir::UR,
+            "UL" => Dir::UL,
+            "DR" => Dir::DR,
+            "DL" => Dir::DL,
+            _ => return None,
+        })
     }
-    else if tail.x == head.x {
-        if rel_posy > 1 {
-            tail.y += 1;
-        }
-        else if rel_posy < -1 {
-            tail.y -= 1;
+
+    fn delta(self) -> (i32, i32) {
+        match self {
+            Dir::R => (1, 0),
+            Dir::L => (-1, 0),
+            Dir::U => (0, 1),
+            Dir::D => (0, -1),
+            Dir::UR => (1, 1),
+            Dir::UL => (-1, 1),
+            Dir::DR => (1, -1),
+            Dir::DL => (-1, -1),
         }
     }
-    else if rel_posx.abs() > 1 || rel_posy.abs() > 1{
-        tail.x += ((rel_posx > 0) as i32) * 2 - 1;
-        tail.y += ((rel_posy > 0) as i32) * 2 - 1;
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    line: usize,
+    text: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid move `{}` on line {}, expected a direction and a number of steps",
+            self.text, self.line
+        )
     }
 }
 
+fn parse_moves(input: &[String]) -> Result<Vec<(Dir, u32)>, ParseError> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, mov)| {
+            let parsed = mov
+                .split_once(' ')
+                .and_then(|(dir, steps)| Some((Dir::parse(dir)?, steps.parse().ok()?)));
+            parsed.ok_or(ParseError {
+                line: i + 1,
+                text: mov.to_string(),
+            })
+        })
+        .collect()
+}
 
-fn fix_rope_pos(rope: &mut Vec<Position>) {
-    for i in 1..rope.len() {
-        let rel_posx = rope.get(i-1).unwrap().x 
-            - rope.get(i).unwrap().x;
-        let rel_posy = rope.get(i-1).unwrap().y
-            - rope.get(i).unwrap().y;
-        
-        if rope.get(i).unwrap().y == rope.get(i-1).unwrap().y {
-            if rel_posx > 1 {
-                rope.get_mut(i).unwrap().x += 1
-            } 
-            else if rel_posx < -1 {
-                rope.get_mut(i).unwrap().x -= 1;
-            }
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct KnotStats {
+    /// Steps in which the knot moved
+    moves: u32,
+    diagonal_moves: u32,
+    /// Largest distance from the start, counting diagonal steps as one
+    max_distance: i32,
+}
+
+/// Knots tied one after the other, the head first, all starting at the
+/// origin. Each knot follows the one before as soon as they stop touching.
+struct Rope {
+    knots: Vec<Position>,
+    stats: Vec<KnotStats>,
+    /// Positions visited by the knots being tracked
+    visited: Vec<Option<HashSet<Position>>>,
+}
+
+impl Rope {
+    fn new(n: usize) -> Self {
+        assert!(n > 0, "A rope needs at least one knot");
+        Self {
+            knots: vec![Position::new(0, 0); n],
+            stats: vec![KnotStats::default(); n],
+            visited: vec![None; n],
         }
-        else if rope.get(i).unwrap().x == rope.get(i-1).unwrap().x {
-            if rel_posy > 1 {
-                rope.get_mut(i).unwrap().y += 1;
-            }
-            else if rel_posy < -1 {
-                rope.get_mut(i).unwrap().y -= 1;
+    }
+
+    /// Starts tracking the positions visited by a knot, from its current one
+    fn track(&mut self, knot: usize) {
+        self.visited[knot].get_or_insert_with(|| HashSet::from([self.knots[knot]]));
+    }
+
+    fn visited(&self, knot: usize) -> Option<&HashSet<Position>> {
+        self.visited[knot].as_ref()
+    }
+
+    fn knots(&self) -> &[Position] {
+        &self.knots
+    }
+
+    fn head(&self) -> Position {
+        self.knots[0]
+    }
+
+    fn tail(&self) -> Position {
+        *self.knots.last().unwrap()
+    }
+
+    fn stats(&self, knot: usize) -> KnotStats {
+        self.stats[knot]
+    }
+
+    /// Moves the head one step, and the rest of the rope after it
+    fn step(&mut self, dir: Dir) {
+        let (dx, dy) = dir.delta();
+        self.move_knot(0, dx, dy);
+
+        for i in 1..self.knots.len() {
+            let (lead, knot) = (self.knots[i - 1], self.knots[i]);
+            let (dx, dy) = (lead.x - knot.x, lead.y - knot.y);
+            if dx.abs() <= 1 && dy.abs() <= 1 {
+                // The knots behind don't move either
+                break;
             }
+            self.move_knot(i, dx.signum(), dy.signum());
         }
-        else if rel_posx.abs() > 1 || rel_posy.abs() > 1{
-            rope.get_mut(i).unwrap().x += ((rel_posx > 0) as i32) * 2 - 1;
-            rope.get_mut(i).unwrap().y += ((rel_posy > 0) as i32) * 2 - 1;
+    }
+
+    fn move_knot(&mut self, i: usize, dx: i32, dy: i32) {
+        let knot = &mut self.knots[i];
+        knot.x += dx;
+        knot.y += dy;
+
+        let stats = &mut self.stats[i];
+        stats.moves += 1;
+        if dx != 0 && dy != 0 {
+            stats.diagonal_moves += 1;
+        }
+        stats.max_distance = stats.max_distance.max(knot.x.abs().max(knot.y.abs()));
+        if let Some(visited) = &mut self.visited[i] {
+            visited.insert(*knot);
         }
     }
 }
 
-#[allow(dead_code)]
-fn visualize_grid(rope: &Vec<Position>) {
-    for col in 0..20 {
-        for row in 0..20 {
-            if rope.contains(&Position::new(row-10, col-10)) {
-                print!("R");
-            }
-            else {
-                print!(".");
-            }
+/// Inclusive rectangle of positions shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    x1: i32,
+    x2: i32,
+    y1: i32,
+    y2: i32,
+}
+
+impl Bounds {
+    /// Smallest rectangle around all the positions
+    fn around<'a>(positions: impl IntoIterator<Item = &'a Position>) -> Self {
+        let start = Position::new(0, 0);
+        positions.into_iter().fold(
+            Bounds {
+                x1: start.x,
+                x2: start.x,
+                y1: start.y,
+                y2: start.y,
+            },
+            |b, p| Bounds {
+                x1: b.x1.min(p.x),
+                x2: b.x2.max(p.x),
+                y1: b.y1.min(p.y),
+                y2: b.y2.max(p.y),
+            },
+        )
+    }
+
+    /// `width` by `height` rectangle with `center` in the middle
+    fn centered(center: Position, width: i32, height: i32) -> Self {
+        let (x1, y1) = (center.x - (width - 1) / 2, center.y - (height - 1) / 2);
+        Bounds {
+            x1,
+            x2: x1 + width - 1,
+            y1,
+            y2: y1 + height - 1,
         }
-        print!("\n");
+    }
+
+    /// Positions row by row, the top row first
+    fn rows(self) -> impl Iterator<Item = Vec<Position>> {
+        (self.y1..=self.y2)
+            .rev()
+            .map(move |y| (self.x1..=self.x2).map(|x| Position::new(x, y)).collect())
     }
 }
 
+/// What is drawn at `pos`: the index of the first knot there (`H` for the
+/// head), `s` for the start, `#` for the trail or `.`
+fn cell(knots: &[Position], trail: &HashSet<Position>, pos: Position) -> char {
+    match knots.iter().position(|&knot| knot == pos) {
+        Some(0) => 'H',
+        Some(i) => char::from_digit(i as u32 % 10, 10).unwrap(),
+        None if pos == Position::new(0, 0) => 's',
+        None if trail.contains(&pos) => '#',
+        None => '.',
+    }
+}
 
-fn part1(input: &Vec<String>) {
-    // Position (x, y)
-    let mut visited: Vec<Position> = vec![Position::new(0, 0)];
-    let mut head = Position::new(0, 0);  
-    let mut tail = Position::new(0, 0);
+fn render_frame(knots: &[Position], trail: &HashSet<Position>, view: Bounds) -> String {
+    let mut out = String::new();
+    for row in view.rows() {
+        out.extend(row.into_iter().map(|pos| cell(knots, trail, pos)));
+        out.push('\n');
+    }
+    out
+}
 
-    for mov in input.iter() {
-        let mut split = mov.split(' ');
-        let direction = split.next().unwrap();
-        let steps = split.next().unwrap().parse::<i32>().unwrap();
+/// Side of the square of pixels drawn for each position
+const PIXELS_PER_CELL: usize = 4;
 
-        for _ in 0..steps {
-            if direction == "R" {
-                head.x += 1;
-            }
-            else if direction == "L" {
-                head.x -= 1;
+/// Plain PPM image of the frame
+fn frame_image(knots: &[Position], trail: &HashSet<Position>, view: Bounds) -> String {
+    let width = (view.x2 - view.x1 + 1) as usize;
+    let height = (view.y2 - view.y1 + 1) as usize;
+    let mut out = format!(
+        "P3\n{} {}\n255\n",
+        width * PIXELS_PER_CELL,
+        height * PIXELS_PER_CELL
+    );
+    for row in view.rows() {
+        let pixels: Vec<&str> = row
+            .into_iter()
+            .flat_map(|pos| {
+                let colour = match cell(knots, trail, pos) {
+                    'H' => "255 64 64",
+                    's' => "0 160 0",
+                    '#' => "96 96 160",
+                    '.' => "24 24 24",
+                    _ => "255 176 0",
+                };
+                [colour; PIXELS_PER_CELL]
+            })
+            .collect();
+        let line = pixels.join(" ") + "\n";
+        out += &line.repeat(PIXELS_PER_CELL);
+    }
+    out
+}
+
+/// Positions of the knots at every step of the moves, the start included
+struct Animation {
+    frames: Vec<Vec<Position>>,
+}
+
+impl Animation {
+    fn record(moves: &[(Dir, u32)], knots: usize) -> Self {
+        let mut rope = Rope::new(knots);
+        let mut frames = vec![rope.knots().to_vec()];
+        for &(dir, steps) in moves {
+            for _ in 0..steps {
+                rope.step(dir);
+                frames.push(rope.knots().to_vec());
             }
-            else if direction == "U" {
-                head.y += 1;
+        }
+        Self { frames }
+    }
+
+    /// Rectangle around everywhere the rope goes
+    fn bounds(&self) -> Bounds {
+        Bounds::around(self.frames.iter().flatten())
+    }
+
+    /// Calls `f` with every `every`th step, the knots, the trail of the tail
+    /// so far and the view. The view is the whole run, or follows the head
+    /// with a `camera` of some width and height.
+    fn play(
+        &self,
+        every: usize,
+        camera: Option<(i32, i32)>,
+        mut f: impl FnMut(usize, &[Position], &HashSet<Position>, Bounds),
+    ) {
+        let bounds = self.bounds();
+        let mut trail = HashSet::new();
+        for (step, knots) in self.frames.iter().enumerate() {
+            trail.insert(*knots.last().unwrap());
+            if step % every != 0 && step != self.frames.len() - 1 {
+                continue;
             }
-            else if direction == "D" {
-                head.y -= 1;
+            let view = match camera {
+                Some((width, height)) => Bounds::centered(knots[0], width, height),
+                None => bounds,
+            };
+            f(step, knots, &trail, view);
+        }
+    }
+}
+
+/// Value of a flag counting knots or steps, which must be above 0
+fn count_flag(flag: &str) -> Result<Option<usize>, String> {
+    match flag_value(flag) {
+        None => Ok(None),
+        Some(n) => match n.parse() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => Err(format!("`{flag}` expects a number above 0")),
+        },
+    }
+}
+
+/// Handles `--animate` and `--frames=DIR`, with `--knots=N`, `--every=N`,
+/// `--camera=WxH` and `--format=txt|ppm`
+fn animate(moves: &[(Dir, u32)]) {
+    let frames_dir = flag_value("--frames");
+    if !has_flag("--animate") && frames_dir.is_none() {
+        return;
+    }
+    let (knots, every) = match (count_flag("--knots"), count_flag("--every")) {
+        (Ok(knots), Ok(every)) => (knots.unwrap_or(10), every.unwrap_or(1)),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    let camera = match flag_value("--camera") {
+        None => None,
+        Some(size) => match size
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        {
+            Some((w, h)) if w > 0 && h > 0 => Some((w, h)),
+            _ => {
+                eprintln!("`--camera` expects a size `WxH` above 0");
+                return;
             }
+        },
+    };
+    let animation = Animation::record(moves, knots);
 
-            fix_tail_pos(&mut tail, &head);
+    if has_flag("--animate") {
+        animation.play(every, camera, |step, knots, trail, view| {
+            println!("\nStep {step}:\n{}", render_frame(knots, trail, view));
+        });
+    }
+    if let Some(dir) = frames_dir {
+        let format = flag_value("--format").unwrap_or(String::from("txt"));
+        if format != "txt" && format != "ppm" {
+            eprintln!("Unknown format `{format}`, expected `txt` or `ppm`");
+            return;
+        }
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            eprintln!("Can't create {dir}: {e}");
+            return;
+        }
 
-            if !visited.contains(&tail) {
-                visited.push(tail);
+        let mut result = Ok(());
+        animation.play(every, camera, |step, knots, trail, view| {
+            let contents = match format.as_str() {
+                "ppm" => frame_image(knots, trail, view),
+                _ => render_frame(knots, trail, view),
+            };
+            let path = format!("{dir}/frame{step:05}.{format}");
+            if result.is_ok() {
+                result = std::fs::write(path, contents);
             }
+        });
+        if let Err(e) = result {
+            eprintln!("Can't write the frames in {dir}: {e}");
         }
     }
-    println!("{}", visited.len())
 }
 
-
-fn part2(input: &Vec<String>) {
-    // Position (x, y)
-    let mut visited: Vec<Position> = vec![Position::new(0, 0)];
-    let mut rope: Vec<Position> = Vec::with_capacity(10);
-    for _ in 0..10 {
-        rope.push(Position::new(0, 0));
+/// The rope after every move, with its tail tracked
+fn pull_rope(moves: &[(Dir, u32)], knots: usize) -> Rope {
+    let mut rope = Rope::new(knots);
+    rope.track(knots - 1);
+    for &(dir, steps) in moves {
+        for _ in 0..steps {
+            rope.step(dir);
+        }
     }
+    rope
+}
+
+fn part1(moves: &[(Dir, u32)]) {
+    let rope = pull_rope(moves, 2);
+    println!("{}", rope.visited(1).unwrap().len())
+}
 
-    for mov in input.iter() {
-        let mut split = mov.split(' ');
-        let direction = split.next().unwrap();
-        let steps = split.next().unwrap().parse::<i32>().unwrap();
+fn part2(moves: &[(Dir, u32)]) {
+    let rope = pull_rope(moves, 10);
+    println!("{}", rope.visited(9).unwrap().len())
+}
 
+/// Prints how much every knot of a rope of `knots` moved
+fn print_stats(moves: &[(Dir, u32)], knots: usize) {
+    let mut rope = Rope::new(knots);
+    for knot in 0..knots {
+        rope.track(knot);
+    }
+    for &(dir, steps) in moves {
         for _ in 0..steps {
-            if direction == "R" {
-                rope.first_mut().unwrap().x += 1;
-            }
-            else if direction == "L" {
-                rope.first_mut().unwrap().x -= 1;
-            }
-            else if direction == "U" {
-                rope.first_mut().unwrap().y += 1;
-            }
-            else if direction == "D" {
-                rope.first_mut().unwrap().y -= 1;
-            }
+            rope.step(dir);
+        }
+    }
 
-            fix_rope_pos(&mut rope);
+    println!("\nknot\tmoves\tdiagonal\tvisited\tmax distance");
+    for knot in 0..knots {
+        let stats = rope.stats(knot);
+        println!(
+            "{knot}\t{}\t{}\t{}\t{}",
+            stats.moves,
+            stats.diagonal_moves,
+            rope.visited(knot).unwrap().len(),
+            stats.max_distance
+        );
+    }
+}
 
-            if !visited.contains(rope.last().unwrap()) {
-                visited.push(rope.last().unwrap().clone());
-            }
+/// A rope whose head is moved one unit at a time by the debugger
+struct RopeSim {
+    moves: Vec<(Dir, u32)>,
+    next_move: usize,
+    steps_left: u32,
+    rope: Rope,
+}
+
+impl RopeSim {
+    fn new(moves: Vec<(Dir, u32)>, knots: usize) -> Self {
+        let mut rope = Rope::new(knots);
+        rope.track(knots - 1);
+        Self {
+            moves,
+            next_move: 0,
+            steps_left: 0,
+            rope,
+        }
+    }
+}
 
-            // visualize_grid(&rope);
-            // println!("------------------------------");
+impl Simulation for RopeSim {
+    fn step(&mut self) -> bool {
+        while self.steps_left == 0 {
+            match self.moves.get(self.next_move) {
+                Some(&(_, steps)) => self.steps_left = steps,
+                None => return false,
+            }
+            self.next_move += 1;
         }
+
+        self.rope.step(self.moves[self.next_move - 1].0);
+        self.steps_left -= 1;
+        true
+    }
+
+    fn render(&self) -> String {
+        let visited = self.rope.visited(self.rope.knots().len() - 1).unwrap();
+        let view = Bounds::around(self.rope.knots().iter().chain(visited.iter()));
+        render_frame(self.rope.knots(), visited, view)
+    }
+
+    fn state(&self) -> Vec<(&'static str, i64)> {
+        let head = self.rope.head();
+        let tail = self.rope.tail();
+        let visited = self.rope.visited(self.rope.knots().len() - 1).unwrap();
+        vec![
+            ("move", self.next_move as i64),
+            ("head_x", head.x as i64),
+            ("head_y", head.y as i64),
+            ("tail_x", tail.x as i64),
+            ("tail_y", tail.y as i64),
+            ("visited", visited.len() as i64),
+        ]
     }
-    println!("{}", visited.len())
 }
 
 fn main() {
@@ -171,10 +511,138 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: Vec<String> = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    let moves = match parse_moves(&input) {
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    if has_flag("--debug") {
+        let knots = if has_flag("--part2") { 10 } else { 2 };
+        debug(RopeSim::new(moves, knots));
+        return;
+    }
+    run_part("PART 1", || part1(&moves));
+    run_part("PART 2", || part2(&moves));
+
+    match count_flag("--stats") {
+        Ok(Some(knots)) => print_stats(&moves, knots),
+        Ok(None) => (),
+        Err(e) => eprintln!("{e}"),
+    }
+    animate(&moves);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn moves(text: &str) -> Vec<(Dir, u32)> {
+        let input: Vec<String> = text.split(',').map(String::from).collect();
+        parse_moves(&input).unwrap()
+    }
+
+    #[test]
+    fn tail_visits() {
+        let example = moves("R 4,U 4,L 3,D 1,R 4,D 1,L 5,R 2");
+        assert_eq!(pull_rope(&example, 2).visited(1).unwrap().len(), 13);
+        assert_eq!(pull_rope(&example, 10).visited(9).unwrap().len(), 1);
+
+        let larger = moves("R 5,U 8,L 8,D 3,R 17,D 10,L 25,U 20");
+        let rope = pull_rope(&larger, 10);
+        assert_eq!(rope.visited(9).unwrap().len(), 36);
+        assert_eq!(rope.head(), Position::new(-11, 15));
+        assert_eq!(rope.tail(), Position::new(-11, 6));
+    }
+
+    #[test]
+    fn diagonal_steps() {
+        let mut rope = Rope::new(3);
+        rope.track(1);
+        for dir in [Dir::UR, Dir::UR, Dir::R, Dir::DL] {
+            rope.step(dir);
+        }
+        assert_eq!(
+            rope.knots(),
+            [
+                Position::new(2, 1),
+                Position::new(2, 2),
+                Position::new(1, 1)
+            ]
+        );
+        assert_eq!(rope.visited(1).unwrap().len(), 3);
+        assert_eq!(rope.visited(2), None);
+        assert_eq!(
+            rope.stats(0),
+            KnotStats {
+                moves: 4,
+                diagonal_moves: 3,
+                max_distance: 3
+            }
+        );
+        assert_eq!(
+            rope.stats(1),
+            KnotStats {
+                moves: 2,
+                diagonal_moves: 2,
+                max_distance: 2
+            }
+        );
+
+        let input = vec!["R 4".to_string(), "X 1".to_string()];
+        assert_eq!(
+            parse_moves(&input).unwrap_err().to_string(),
+            "Invalid move `X 1` on line 2, expected a direction and a number of steps"
+        );
+    }
+
+    #[test]
+    fn frames() {
+        let example = moves("R 4,U 4,L 3,D 1,R 4,D 1,L 5,R 2");
+        let animation = Animation::record(&example, 10);
+        assert_eq!(animation.frames.len(), 25);
+        assert_eq!(
+            animation.bounds(),
+            Bounds {
+                x1: 0,
+                x2: 5,
+                y1: 0,
+                y2: 4
+            }
+        );
+
+        let mut rendered = vec![];
+        animation.play(8, None, |step, knots, trail, view| {
+            rendered.push((step, render_frame(knots, trail, view)));
+        });
+        let steps: Vec<usize> = rendered.iter().map(|r| r.0).collect();
+        assert_eq!(steps, [0, 8, 16, 24]);
+        assert_eq!(rendered[1].1, "....H.\n....1.\n..432.\n.5....\n6.....\n");
+        // The 6 hides the rest of the rope and the start
+        assert_eq!(rendered[3].1, "......\n......\n.1H3..\n.5....\n6.....\n");
+
+        let mut views = vec![];
+        animation.play(8, Some((3, 2)), |_, knots, _, view| {
+            views.push((knots[0], view))
+        });
+        let (head, view) = views[1];
+        assert_eq!(head, Position::new(4, 4));
+        assert_eq!(
+            view,
+            Bounds {
+                x1: 3,
+                x2: 5,
+                y1: 4,
+                y2: 5
+            }
+        );
+
+        let image = frame_image(&[], &HashSet::new(), view);
+        assert!(image.starts_with("P3\n12 8\n255\n24 24 24 "));
+        assert_eq!(image.lines().count(), 3 + 8);
+    }
 }
@@ -1,99 +1,429 @@
-use aoc::read_inputs;
+use aoc::ocr::{read_letters, UnknownGlyph};
+use aoc::{flag_value, has_flag, input_arg, read_inputs, run_part};
+use std::collections::HashMap;
+use std::fmt;
 
-fn part1(input: &Vec<String>) {
-    let mut x = 1;
-    let mut signal_strength_sum = 0;
-    let mut input_iter = input.iter();
-    let mut instruction = input_iter.next().unwrap();
-    let mut cycle_to_read: Option<i32> = None;
-    let mut cycle_to_add: Option<i32> = None;
-    let mut add_number: Option<i32> = None;
+/// Jumps are relative to the instruction doing them
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Instruction {
+    Noop,
+    Addx(i32),
+    Addy(i32),
+    Jmp(i32),
+    /// Jumps if Y is not 0
+    Jnz(i32),
+    Halt,
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    line: usize,
+    text: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid instruction `{}` on line {}",
+            self.text, self.line
+        )
+    }
+}
 
-    for cycle in 1..221 {
-        if (cycle + 20) % 40 == 0 {
-            // println!("Cycle {}: signal strenth = {}", cycle, x*cycle);
-            signal_strength_sum += x * cycle;
+impl Instruction {
+    fn parse(text: &str, line: usize) -> Result<Self, ParseError> {
+        let invalid = || ParseError {
+            line,
+            text: text.to_string(),
+        };
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let number = |n: &str| n.parse::<i32>().map_err(|_| invalid());
+        match words[..] {
+            ["noop"] => Ok(Instruction::Noop),
+            ["addx", n] => Ok(Instruction::Addx(number(n)?)),
+            ["addy", n] => Ok(Instruction::Addy(number(n)?)),
+            ["jmp", n] => Ok(Instruction::Jmp(number(n)?)),
+            ["jnz", n] => Ok(Instruction::Jnz(number(n)?)),
+            ["halt"] => Ok(Instruction::Halt),
+            _ => Err(invalid()),
         }
+    }
 
-        if Some(cycle) == cycle_to_read {
-            instruction = input_iter.next().unwrap();
+    /// Cycles it takes to run
+    fn cycles(self) -> u32 {
+        match self {
+            Instruction::Addx(_) | Instruction::Addy(_) => 2,
+            Instruction::Noop | Instruction::Jmp(_) | Instruction::Jnz(_) | Instruction::Halt => 1,
         }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Noop => write!(f, "noop"),
+            Instruction::Addx(n) => write!(f, "addx {n}"),
+            Instruction::Addy(n) => write!(f, "addy {n}"),
+            Instruction::Jmp(n) => write!(f, "jmp {n}"),
+            Instruction::Jnz(n) => write!(f, "jnz {n}"),
+            Instruction::Halt => write!(f, "halt"),
+        }
+    }
+}
+
+fn parse_program(input: &[String]) -> Result<Vec<Instruction>, ParseError> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, ln)| Instruction::parse(ln, i + 1))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Registers {
+    x: i32,
+    y: i32,
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self { x: 1, y: 0 }
+    }
+}
+
+/// State of the CPU during a cycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tick {
+    /// Number of the cycle, from 1
+    cycle: u32,
+    /// Cycle in which the running instruction started
+    start: u32,
+    /// Index in the program of the instruction running
+    pc: usize,
+    instruction: Instruction,
+    regs: Registers,
+}
+
+/// Hook called during every cycle, before the running instruction is done
+trait Observer {
+    fn on_cycle(&mut self, tick: &Tick);
+}
+
+impl<F: FnMut(&Tick)> Observer for F {
+    fn on_cycle(&mut self, tick: &Tick) {
+        self(tick)
+    }
+}
+
+struct Cpu {
+    program: Vec<Instruction>,
+    pc: usize,
+    /// Cycles done so far
+    cycle: u32,
+    regs: Registers,
+    halted: bool,
+}
 
-        // println!("Cycle {}: x={}    {}", cycle, x, instruction);
-        let mut split = instruction.split(' ');
-        let command = split.next().unwrap();
+impl Cpu {
+    fn new(program: Vec<Instruction>) -> Self {
+        Self {
+            program,
+            pc: 0,
+            cycle: 0,
+            regs: Registers::default(),
+            halted: false,
+        }
+    }
 
-        if Some(cycle) == cycle_to_add {
-            x += add_number.unwrap();
-            add_number = None;
-            cycle_to_add = None;
-            cycle_to_read = Some(cycle + 1);
-            continue;
+    /// Runs the next instruction, calling the observers during each of its
+    /// cycles. `false` once the program is over: after a `halt`, or when the
+    /// next instruction is out of it.
+    fn step(&mut self, observers: &mut [&mut dyn Observer]) -> bool {
+        let Some(&instruction) = self.program.get(self.pc).filter(|_| !self.halted) else {
+            return false;
+        };
+        let start = self.cycle + 1;
+        for _ in 0..instruction.cycles() {
+            self.cycle += 1;
+            let tick = Tick {
+                cycle: self.cycle,
+                start,
+                pc: self.pc,
+                instruction,
+                regs: self.regs,
+            };
+            for observer in observers.iter_mut() {
+                observer.on_cycle(&tick);
+            }
         }
 
-        if command == "noop" {
-            cycle_to_read = Some(cycle + 1);
+        let mut offset = 1;
+        match instruction {
+            Instruction::Noop => (),
+            Instruction::Addx(n) => self.regs.x += n,
+            Instruction::Addy(n) => self.regs.y += n,
+            Instruction::Jmp(n) => offset = n,
+            Instruction::Jnz(n) if self.regs.y != 0 => offset = n,
+            Instruction::Jnz(_) => (),
+            Instruction::Halt => self.halted = true,
         }
+        // Jumping before the start ends the program like jumping after the end
+        self.pc = self
+            .pc
+            .checked_add_signed(offset as isize)
+            .unwrap_or(usize::MAX);
+        true
+    }
 
-        if command == "addx" {
-            if add_number.is_none() {
-                add_number = Some(split.next().unwrap().parse::<i32>().unwrap());
-                cycle_to_add = Some(cycle + 1);
+    /// Runs until the program is over or `max_cycles` have been done, the last
+    /// instruction being finished, and whether the program is over
+    fn run_for(&mut self, max_cycles: u32, observers: &mut [&mut dyn Observer]) -> bool {
+        while self.cycle < max_cycles {
+            if !self.step(observers) {
+                return true;
             }
         }
+        false
     }
-    println!("{}", signal_strength_sum);
 }
 
-fn part2(input: &Vec<String>) {
-    let mut x = 1;
-    let mut input_iter = input.iter();
-    let mut instruction = input_iter.next().unwrap();
-    let mut cycle_to_read: Option<i32> = None;
-    let mut cycle_to_add: Option<i32> = None;
-    let mut add_number: Option<i32> = None;
-    let mut crt_pos = 0;
+/// Sum of the signal strengths (cycle times X) during cycles 20, 60, ... 220
+#[derive(Default)]
+struct SignalStrength {
+    sum: i64,
+}
 
-    for cycle in 1..40 * 6 + 1 {
-        if x - 1 <= crt_pos && crt_pos <= x + 1 {
-            print!("#");
-        } else {
-            print!(".");
+impl Observer for SignalStrength {
+    fn on_cycle(&mut self, tick: &Tick) {
+        if tick.cycle % 40 == 20 && tick.cycle <= 220 {
+            self.sum += tick.cycle as i64 * tick.regs.x as i64;
         }
-        crt_pos = (crt_pos + 1) % 40;
-        if crt_pos % 40 == 0 {
-            println!();
+    }
+}
+
+const CRT_WIDTH: usize = 40;
+const CRT_HEIGHT: usize = 6;
+
+/// Screen drawing a pixel per cycle, lit if the 3 pixels wide sprite centered
+/// on X covers it
+struct Crt {
+    pixels: Vec<bool>,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self {
+            pixels: vec![false; CRT_WIDTH * CRT_HEIGHT],
         }
+    }
+}
 
-        if Some(cycle) == cycle_to_read {
-            instruction = input_iter.next().unwrap();
+impl Observer for Crt {
+    fn on_cycle(&mut self, tick: &Tick) {
+        let pos = (tick.cycle as usize - 1) % self.pixels.len();
+        let col = (pos % CRT_WIDTH) as i32;
+        self.pixels[pos] = (tick.regs.x - col).abs() <= 1;
+    }
+}
+
+impl Crt {
+    /// Letters drawn on the screen
+    fn text(&self) -> Result<String, UnknownGlyph> {
+        read_letters(&self.pixels, CRT_WIDTH)
+    }
+}
+
+impl fmt::Display for Crt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.pixels.chunks(CRT_WIDTH) {
+            let row: String = row.iter().map(|&lit| if lit { '#' } else { '.' }).collect();
+            writeln!(f, "{row}")?;
         }
+        Ok(())
+    }
+}
+
+/// A line for every cycle
+#[derive(Default)]
+struct Tracer {
+    lines: Vec<String>,
+}
 
-        // println!("Cycle {}: x={}    {}", cycle, x, instruction);
-        let mut split = instruction.split(' ');
-        let command = split.next().unwrap();
+impl Observer for Tracer {
+    fn on_cycle(&mut self, tick: &Tick) {
+        self.lines.push(format!(
+            "cycle {:>3}  pc {:>3}  X={:<4}  Y={:<4}  {}",
+            tick.cycle, tick.pc, tick.regs.x, tick.regs.y, tick.instruction
+        ));
+    }
+}
 
-        if Some(cycle) == cycle_to_add {
-            x += add_number.unwrap();
-            add_number = None;
-            cycle_to_add = None;
-            cycle_to_read = Some(cycle + 1);
-            continue;
+#[derive(Debug, PartialEq)]
+enum AsmError {
+    UnknownInstruction { line: usize, name: String },
+    InvalidOperand { line: usize, text: String },
+    UnknownLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::UnknownInstruction { line, name } => {
+                write!(f, "Unknown instruction `{name}` on line {line}")
+            }
+            AsmError::InvalidOperand { line, text } => {
+                write!(f, "Invalid operands in `{text}` on line {line}")
+            }
+            AsmError::UnknownLabel { line, label } => {
+                write!(f, "Unknown label `{label}` on line {line}")
+            }
+            AsmError::DuplicateLabel { line, label } => {
+                write!(f, "Label `{label}` on line {line} is already defined")
+            }
         }
+    }
+}
 
-        if command == "noop" {
-            cycle_to_read = Some(cycle + 1);
+/// Assembles a program in the puzzle format, extended with:
+/// - comments, from `;` or `#` to the end of the line
+/// - labels, as `name:` alone on a line or before an instruction
+/// - `jmp` and `jnz` to a label instead of a relative offset
+fn assemble(source: &[String]) -> Result<Vec<Instruction>, AsmError> {
+    // Instructions with their line, then labels with the index they point to
+    let mut lines: Vec<(usize, &str)> = vec![];
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    for (i, ln) in source.iter().enumerate() {
+        let mut text = ln.split(['#', ';']).next().unwrap_or_default().trim();
+        if let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if labels.insert(label, lines.len()).is_some() {
+                return Err(AsmError::DuplicateLabel {
+                    line: i + 1,
+                    label: label.to_string(),
+                });
+            }
+            text = rest.trim();
+        }
+        if !text.is_empty() {
+            lines.push((i + 1, text));
         }
+    }
 
-        if command == "addx" {
-            if add_number.is_none() {
-                add_number = Some(split.next().unwrap().parse::<i32>().unwrap());
-                cycle_to_add = Some(cycle + 1);
+    let mut program = vec![];
+    for (pc, &(line, text)) in lines.iter().enumerate() {
+        let invalid = || AsmError::InvalidOperand {
+            line,
+            text: text.to_string(),
+        };
+        let number = |n: &str| n.parse::<i32>().map_err(|_| invalid());
+        // A label, or an offset from the instruction
+        let target = |n: &str| match labels.get(n) {
+            Some(&index) => Ok(index as i32 - pc as i32),
+            None if n.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') => {
+                number(n)
             }
+            None => Err(AsmError::UnknownLabel {
+                line,
+                label: n.to_string(),
+            }),
+        };
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let instruction = match (words[0], &words[1..]) {
+            ("noop", []) => Instruction::Noop,
+            ("halt", []) => Instruction::Halt,
+            ("addx", [n]) => Instruction::Addx(number(n)?),
+            ("addy", [n]) => Instruction::Addy(number(n)?),
+            ("jmp", [n]) => Instruction::Jmp(target(n)?),
+            ("jnz", [n]) => Instruction::Jnz(target(n)?),
+            ("noop" | "halt" | "addx" | "addy" | "jmp" | "jnz", _) => return Err(invalid()),
+            (name, _) => {
+                return Err(AsmError::UnknownInstruction {
+                    line,
+                    name: name.to_string(),
+                })
+            }
+        };
+        program.push(instruction);
+    }
+    Ok(program)
+}
+
+/// Cycle each instruction starts at and X during it, for every time it runs
+struct Profile {
+    starts: Vec<Vec<(u32, i32)>>,
+}
+
+impl Profile {
+    fn new(program: &[Instruction]) -> Self {
+        Self {
+            starts: vec![vec![]; program.len()],
         }
     }
 }
 
+impl Observer for Profile {
+    fn on_cycle(&mut self, tick: &Tick) {
+        if tick.cycle == tick.start {
+            self.starts[tick.pc].push((tick.cycle, tick.regs.x));
+        }
+    }
+}
+
+/// The program annotated with when each instruction runs, within the first
+/// `max_cycles`. The annotations are comments, so it can be assembled back.
+fn disassemble(program: &[Instruction], max_cycles: u32) -> Vec<String> {
+    let mut profile = Profile::new(program);
+    Cpu::new(program.to_vec()).run_for(max_cycles, &mut [&mut profile]);
+    program
+        .iter()
+        .zip(&profile.starts)
+        .enumerate()
+        .map(|(pc, (instruction, starts))| {
+            let runs: Vec<String> = starts
+                .iter()
+                .map(|(cycle, x)| format!("cycle {cycle} X={x}"))
+                .collect();
+            let runs = if runs.is_empty() {
+                "never run".to_string()
+            } else {
+                runs.join(", ")
+            };
+            format!("{:<10}  ; {pc:>3}: {runs}", instruction.to_string())
+        })
+        .collect()
+}
+
+/// Cycles after which programs are stopped, as they may loop forever
+const MAX_CYCLES: u32 = 100_000;
+
+/// Runs the program for at most `max_cycles`, telling if it was stopped
+fn run(program: &[Instruction], max_cycles: u32, observers: &mut [&mut dyn Observer]) {
+    if !Cpu::new(program.to_vec()).run_for(max_cycles, observers) {
+        eprintln!("Program stopped after {max_cycles} cycles");
+    }
+}
+
+fn part1(program: &[Instruction], max_cycles: u32) {
+    let mut signal = SignalStrength::default();
+    run(program, max_cycles, &mut [&mut signal]);
+    println!("{}", signal.sum);
+}
+
+fn part2(program: &[Instruction], max_cycles: u32) {
+    let mut crt = Crt::default();
+    run(program, max_cycles, &mut [&mut crt]);
+    match crt.text() {
+        Ok(text) => println!("{text}"),
+        Err(e) => eprint!("{e}\nin the screen:\n{crt}"),
+    }
+    if has_flag("--screen") {
+        print!("{crt}");
+    }
+}
+
 fn main() {
     let day: u32 = 10;
     #[cfg(debug_assertions)]
@@ -101,10 +431,181 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: Vec<String> = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    let program = if has_flag("--assemble") {
+        assemble(&input).map_err(|e| e.to_string())
+    } else {
+        parse_program(&input).map_err(|e| e.to_string())
+    };
+    let program = match program {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    let max_cycles = match flag_value("--max-cycles").map(|n| n.parse()) {
+        None => MAX_CYCLES,
+        Some(Ok(n)) => n,
+        Some(Err(_)) => {
+            eprintln!("`--max-cycles` expects a number of cycles");
+            return;
+        }
+    };
+    run_part("PART 1", || part1(&program, max_cycles));
+    run_part("PART 2", || part2(&program, max_cycles));
+
+    if has_flag("--assemble") {
+        println!("\nProgram:");
+        for instruction in &program {
+            println!("{instruction}");
+        }
+    }
+    if has_flag("--disassemble") {
+        println!("\n{}", disassemble(&program, max_cycles).join("\n"));
+    }
+    if has_flag("--trace") {
+        let mut tracer = Tracer::default();
+        run(&program, max_cycles, &mut [&mut tracer]);
+        println!("\n{}", tracer.lines.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn program(lines: &[&str]) -> Vec<Instruction> {
+        let input: Vec<String> = lines.iter().map(|ln| ln.to_string()).collect();
+        parse_program(&input).unwrap()
+    }
+
+    fn example() -> Vec<Instruction> {
+        let input = std::fs::read_to_string("data/examples/10.txt").unwrap();
+        program(&input.lines().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn cycles() {
+        let mut ticks = vec![];
+        let mut record = |tick: &Tick| ticks.push((tick.cycle, tick.regs.x));
+        let mut cpu = Cpu::new(program(&["noop", "addx 3", "addx -5"]));
+        assert!(cpu.run_for(MAX_CYCLES, &mut [&mut record]));
+        assert_eq!(ticks, [(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+        assert_eq!(cpu.regs.x, -1);
+        assert!(!cpu.step(&mut []));
+
+        let input = vec!["noop".to_string(), "addx x".to_string()];
+        assert_eq!(
+            parse_program(&input).unwrap_err().to_string(),
+            "Invalid instruction `addx x` on line 2"
+        );
+    }
+
+    #[test]
+    fn observers() {
+        let mut signal = SignalStrength::default();
+        let mut crt = Crt::default();
+        let mut tracer = Tracer::default();
+        let mut cpu = Cpu::new(example());
+        assert!(cpu.run_for(MAX_CYCLES, &mut [&mut signal, &mut crt, &mut tracer]));
+
+        assert_eq!(signal.sum, 13140);
+        assert_eq!(
+            crt.to_string().lines().next(),
+            Some("##..##..##..##..##..##..##..##..##..##..")
+        );
+        assert_eq!(crt.text().unwrap_err().rows[0], "##..");
+        assert_eq!(tracer.lines.len(), 240);
+        assert_eq!(
+            tracer.lines[1],
+            "cycle   2  pc   0  X=1     Y=0     addx 15"
+        );
+    }
+
+    #[test]
+    fn extended_instructions() {
+        // Adds 1 to X three times, then halts before the last addx
+        let mut cpu = Cpu::new(program(&[
+            "addy 3", "addx 1", "addy -1", "jnz -2", "halt", "addx 10",
+        ]));
+        assert!(cpu.run_for(100, &mut []));
+        assert_eq!(cpu.regs, Registers { x: 4, y: 0 });
+        assert_eq!(cpu.pc, 5);
+        assert_eq!(cpu.cycle, 2 + 3 * 5 + 1);
+
+        let mut cpu = Cpu::new(program(&["addx 1", "jmp -1"]));
+        // The instruction running at the limit is finished
+        assert!(!cpu.run_for(10, &mut []));
+        assert_eq!((cpu.cycle, cpu.regs.x), (11, 5));
+        let mut cpu = Cpu::new(program(&["noop", "jmp -2", "noop"]));
+        assert!(cpu.run_for(10, &mut []));
+        assert_eq!(cpu.cycle, 2);
+    }
+
+    #[test]
+    fn assembler() {
+        let source: Vec<String> = [
+            "; count down from 3",
+            "    addy 3",
+            "loop: addx 2  # twice a cycle",
+            "    addy -1",
+            "    jnz loop",
+            "end:",
+            "    jmp end",
+            "    halt",
+        ]
+        .iter()
+        .map(|ln| ln.to_string())
+        .collect();
+        let assembled = assemble(&source).unwrap();
+        let text: Vec<String> = assembled.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            text,
+            ["addy 3", "addx 2", "addy -1", "jnz -2", "jmp 0", "halt"]
+        );
+        // The puzzle format is valid assembly
+        assert_eq!(assemble(&text).unwrap(), assembled);
+        assert_eq!(parse_program(&text).unwrap(), assembled);
+
+        let error = |lines: &[&str]| {
+            let source: Vec<String> = lines.iter().map(|ln| ln.to_string()).collect();
+            assemble(&source).unwrap_err().to_string()
+        };
+        assert_eq!(
+            error(&["noop", "mul 2"]),
+            "Unknown instruction `mul` on line 2"
+        );
+        assert_eq!(error(&["addx"]), "Invalid operands in `addx` on line 1");
+        assert_eq!(error(&["halt 1"]), "Invalid operands in `halt 1` on line 1");
+        assert_eq!(error(&["jmp start"]), "Unknown label `start` on line 1");
+        assert_eq!(
+            error(&["a: noop", "a:"]),
+            "Label `a` on line 2 is already defined"
+        );
+    }
+
+    #[test]
+    fn disassembler() {
+        let lines = disassemble(
+            &program(&[
+                "addy 2", "addx 3", "addy -1", "jnz -2", "jmp 2", "noop", "addx 1",
+            ]),
+            100,
+        );
+        assert_eq!(
+            lines,
+            [
+                "addy 2      ;   0: cycle 1 X=1",
+                "addx 3      ;   1: cycle 3 X=1, cycle 8 X=4",
+                "addy -1     ;   2: cycle 5 X=4, cycle 10 X=7",
+                "jnz -2      ;   3: cycle 7 X=4, cycle 12 X=7",
+                "jmp 2       ;   4: cycle 13 X=7",
+                "noop        ;   5: never run",
+                "addx 1      ;   6: cycle 14 X=7",
+            ]
+        );
+    }
 }
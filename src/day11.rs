@@ -1,165 +1,812 @@
-use aoc::read_inputs;
+use aoc::math::{checked_lcm, mul_mod};
+use aoc::{flag_value, has_flag, input_arg, read_inputs, run_part};
+use num::integer::Integer;
+use num::{BigInt, BigUint, Zero};
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter::Peekable;
 
 type InputT = Vec<String>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    fn precedence(self) -> u8 {
+        match self {
+            Op::Add | Op::Sub => 1,
+            Op::Mul | Op::Div => 2,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+        };
+        write!(f, "{c}")
+    }
+}
+
+/// Operation of a monkey, giving the new worry level from the `old` one
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Old,
+    Const(u64),
+    Binary(Box<Expr>, Op, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Old,
+    Num(u64),
+    Op(Op),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Old => write!(f, "old"),
+            Token::Num(n) => write!(f, "{n}"),
+            Token::Op(op) => write!(f, "{op}"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c.is_alphanumeric() {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_alphanumeric()) {
+                word.push(c);
+            }
+            tokens.push(match word.parse() {
+                Ok(n) => Token::Num(n),
+                Err(_) if word == "old" => Token::Old,
+                Err(_) => return Err(format!("unknown term `{word}`")),
+            });
+            continue;
+        }
+        tokens.push(match c {
+            '+' => Token::Op(Op::Add),
+            '-' => Token::Op(Op::Sub),
+            '*' => Token::Op(Op::Mul),
+            '/' => Token::Op(Op::Div),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            _ => return Err(format!("unexpected `{c}`")),
+        });
+        chars.next();
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens, `*` and `/` binding tighter than `+`
+/// and `-`, all of them left associative
+struct ExprParser<I: Iterator<Item = Token>> {
+    tokens: Peekable<I>,
+}
+
+impl<I: Iterator<Item = Token>> ExprParser<I> {
+    fn binary(
+        &mut self,
+        precedence: u8,
+        operand: fn(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut expr = operand(self)?;
+        while let Some(Token::Op(op)) = self
+            .tokens
+            .next_if(|t| matches!(t, Token::Op(op) if op.precedence() == precedence))
+        {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(operand(self)?));
+        }
+        Ok(expr)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(1, Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        self.binary(2, Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        match self.tokens.next() {
+            Some(Token::Old) => Ok(Expr::Old),
+            Some(Token::Num(n)) => Ok(Expr::Const(n)),
+            Some(Token::Open) => {
+                let expr = self.sum()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(t) => Err(format!("expected `)`, found `{t}`")),
+                    None => Err("expected `)`, found the end".to_string()),
+                }
+            }
+            Some(t) => Err(format!("expected `old`, a number or `(`, found `{t}`")),
+            None => Err("expected `old`, a number or `(`, found the end".to_string()),
+        }
+    }
+}
+
+impl Expr {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = ExprParser {
+            tokens: tokenize(text)?.into_iter().peekable(),
+        };
+        let expr = parser.sum()?;
+        match parser.tokens.next() {
+            Some(t) => Err(format!("unexpected `{t}`")),
+            None => Ok(expr),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary(_, op, _) => op.precedence(),
+            _ => u8::MAX,
+        }
+    }
+
+    /// Value for the `old` worry level, with `constant` turning numbers into
+    /// worry levels and `apply` doing the operations
+    fn eval<T: Clone, E>(
+        &self,
+        old: &T,
+        constant: &impl Fn(u64) -> T,
+        apply: &impl Fn(Op, T, T) -> Result<T, E>,
+    ) -> Result<T, E> {
+        match self {
+            Expr::Old => Ok(old.clone()),
+            Expr::Const(n) => Ok(constant(*n)),
+            Expr::Binary(a, op, b) => {
+                let a = a.eval(old, constant, apply)?;
+                let b = b.eval(old, constant, apply)?;
+                apply(*op, a, b)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Const(n) => write!(f, "{n}"),
+            Expr::Binary(a, op, b) => {
+                // Operations are left associative, so the right operand needs
+                // parentheses from the same precedence on
+                if a.precedence() < op.precedence() {
+                    write!(f, "({a})")?;
+                } else {
+                    write!(f, "{a}")?;
+                }
+                write!(f, " {op} ")?;
+                if b.precedence() <= op.precedence() {
+                    write!(f, "({b})")
+                } else {
+                    write!(f, "{b}")
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Monkey<T> {
-    items: Vec<T>,    // Items' held worry level
-    op: (char, u32),  // Operation and number (e.g. ('*', 19))
-    test_div: u32,    // Number to test divisibility
-    if_true: usize,   // Index of monkey to throw to if true
-    if_false: usize,  // Index of monkey to throw to if false
-    inspections: u64, // Number of inspections
-}
-
-impl<T> Monkey<T> {
-    fn new() -> Self {
-        Self {
-            items: vec![],
-            op: ('*', 0),
-            test_div: 0,
-            if_true: 0,
-            if_false: 0,
-            inspections: 0,
+    items: VecDeque<T>, // Items' held worry level, in inspection order
+    op: Expr,           // Operation giving the new worry level
+    test_div: u64,      // Number to test divisibility
+    if_true: usize,     // Index of monkey to throw to if true
+    if_false: usize,    // Index of monkey to throw to if false
+    inspections: u64,   // Number of inspections
+}
+
+impl Monkey<u64> {
+    /// The same monkey with its worry levels converted by `f`
+    fn map_items<T>(&self, f: impl Fn(u64) -> T) -> Monkey<T> {
+        Monkey {
+            items: self.items.iter().map(|&item| f(item)).collect(),
+            op: self.op.clone(),
+            test_div: self.test_div,
+            if_true: self.if_true,
+            if_false: self.if_false,
+            inspections: self.inspections,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    NoMonkeys,
+    MissingLine {
+        expected: &'static str,
+    },
+    InvalidLine {
+        line: usize,
+        text: String,
+        expected: &'static str,
+    },
+    InvalidOperation {
+        line: usize,
+        reason: String,
+    },
+    InvalidTarget {
+        monkey: usize,
+        target: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NoMonkeys => write!(f, "There are no monkeys"),
+            ParseError::MissingLine { expected } => {
+                write!(f, "The input ends early, expected `{expected}`")
+            }
+            ParseError::InvalidLine {
+                line,
+                text,
+                expected,
+            } => write!(f, "Invalid line {line} `{text}`, expected `{expected}`"),
+            ParseError::InvalidOperation { line, reason } => {
+                write!(f, "Invalid operation on line {line}: {reason}")
+            }
+            ParseError::InvalidTarget { monkey, target } if monkey == target => {
+                write!(f, "Monkey {monkey} throws to itself")
+            }
+            ParseError::InvalidTarget { monkey, target } => {
+                write!(
+                    f,
+                    "Monkey {monkey} throws to monkey {target}, which doesn't exist"
+                )
+            }
         }
     }
 }
 
-fn create_monkey_list<T: std::str::FromStr>(input: &InputT) -> Vec<Monkey<T>> {
-    let mut monkeys: Vec<Monkey<T>> = vec![];
-    let mut iter = input.iter();
+/// Reads the next line as `prefix` followed by a value, returning its line
+/// number and value
+fn field<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    prefix: &str,
+    expected: &'static str,
+) -> Result<(usize, &'a str), ParseError> {
+    let (line, text) = lines.next().ok_or(ParseError::MissingLine { expected })?;
+    let value = text
+        .strip_prefix(prefix)
+        .ok_or_else(|| ParseError::InvalidLine {
+            line,
+            text: text.to_string(),
+            expected,
+        })?;
+    Ok((line, value.trim()))
+}
+
+/// Like [`field`], for a number
+fn number_field<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    prefix: &str,
+    expected: &'static str,
+) -> Result<u64, ParseError> {
+    let (line, value) = field(lines, prefix, expected)?;
+    value.parse().map_err(|_| ParseError::InvalidLine {
+        line,
+        text: format!("{prefix}{value}"),
+        expected,
+    })
+}
+
+fn parse_monkeys(input: &[String]) -> Result<Vec<Monkey<u64>>, ParseError> {
+    let mut lines = input
+        .iter()
+        .enumerate()
+        .map(|(i, ln)| (i + 1, ln.trim()))
+        .filter(|(_, ln)| !ln.is_empty())
+        .peekable();
+
+    let mut monkeys = vec![];
+    while lines.peek().is_some() {
+        let expected = "Monkey N:";
+        let (line, value) = field(&mut lines, "Monkey ", expected)?;
+        if value != format!("{}:", monkeys.len()) {
+            return Err(ParseError::InvalidLine {
+                line,
+                text: format!("Monkey {value}"),
+                expected,
+            });
+        }
+
+        let expected = "Starting items: N, ...";
+        let (line, value) = field(&mut lines, "Starting items:", expected)?;
+        let items = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| ParseError::InvalidLine {
+                line,
+                text: format!("Starting items: {value}"),
+                expected,
+            })?;
 
-    while let Some(line) = iter.next() {
-        if line.contains("Monkey") {
-            let mut monkey = Monkey::new();
+        let (line, value) = field(
+            &mut lines,
+            "Operation: new =",
+            "Operation: new = EXPRESSION",
+        )?;
+        let op =
+            Expr::parse(value).map_err(|reason| ParseError::InvalidOperation { line, reason })?;
 
-            // Starting items parse
-            let mut line = iter.next().unwrap();
-            let (_, items_str) = line.split_once(": ").unwrap();
-            for item in items_str.split(", ") {
-                monkey.items.push(item.parse::<T>().ok().unwrap());
+        let test_div = number_field(&mut lines, "Test: divisible by ", "Test: divisible by N")?;
+        if test_div == 0 {
+            return Err(ParseError::InvalidLine {
+                line: line + 1,
+                text: "Test: divisible by 0".to_string(),
+                expected: "Test: divisible by N, with N > 0",
+            });
+        }
+        let if_true = number_field(
+            &mut lines,
+            "If true: throw to monkey ",
+            "If true: throw to monkey N",
+        )?;
+        let if_false = number_field(
+            &mut lines,
+            "If false: throw to monkey ",
+            "If false: throw to monkey N",
+        )?;
+
+        monkeys.push(Monkey {
+            items,
+            op,
+            test_div,
+            if_true: if_true as usize,
+            if_false: if_false as usize,
+            inspections: 0,
+        });
+    }
+
+    if monkeys.is_empty() {
+        return Err(ParseError::NoMonkeys);
+    }
+    for (i, monkey) in monkeys.iter().enumerate() {
+        for target in [monkey.if_true, monkey.if_false] {
+            if target == i || target >= monkeys.len() {
+                return Err(ParseError::InvalidTarget { monkey: i, target });
             }
+        }
+    }
+    Ok(monkeys)
+}
+
+/// How worry levels go down after every inspection
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Relief {
+    /// Divided by the number (above 0), rounding down. Levels are kept in 64
+    /// bits, and in big integers only if they don't fit.
+    Divide(u64),
+    /// Kept modulo the lcm of the monkeys' divisors, which doesn't change any
+    /// divisibility test. Operations can't divide.
+    Modulo,
+    /// Not at all, with big integers
+    None,
+}
+
+impl fmt::Display for Relief {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Relief::Divide(n) => write!(f, "divided by {n}"),
+            Relief::Modulo => write!(f, "modulo the lcm"),
+            Relief::None => write!(f, "no relief"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SimError {
+    ModulusOverflow,
+    LevelOverflow { monkey: usize },
+    DivisionByZero { monkey: usize },
+    ModularDivision { monkey: usize },
+}
 
-            // Operation parse
-            line = iter.next().unwrap();
-            let (_, op_str) = line.split_once("old ").unwrap();
-            let split = op_str.split_once(' ').unwrap();
-            monkey.op.0 = split.0.chars().nth(0).unwrap();
-            if let Some(num) = split.1.parse::<u32>().ok() {
-                monkey.op.1 = num;
-            } else {
-                // `new = old * old` case (square)
-                monkey.op.0 = '^';
-                monkey.op.1 = 2;
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimError::ModulusOverflow => {
+                write!(f, "The lcm of the monkeys' divisors doesn't fit in 64 bits")
             }
+            SimError::LevelOverflow { monkey } => {
+                write!(f, "Monkey {monkey} takes a worry level out of 64 bits")
+            }
+            SimError::DivisionByZero { monkey } => {
+                write!(f, "Monkey {monkey} divides a worry level by 0")
+            }
+            SimError::ModularDivision { monkey } => write!(
+                f,
+                "Monkey {monkey} divides, which can't be done modulo the lcm"
+            ),
+        }
+    }
+}
+
+/// How worry levels are stored and computed for a relief strategy
+trait Arithmetic {
+    type Level: Clone;
 
-            // Test parse
-            line = iter.next().unwrap();
-            let (_, test_div_str) = line.split_once("by ").unwrap();
-            monkey.test_div = test_div_str.parse().ok().unwrap();
+    fn level(&self, n: u64) -> Self::Level;
+    fn apply(
+        &self,
+        op: Op,
+        a: Self::Level,
+        b: Self::Level,
+        monkey: usize,
+    ) -> Result<Self::Level, SimError>;
+    fn relieve(&self, level: Self::Level) -> Self::Level;
+    fn is_divisible(&self, level: &Self::Level, n: u64) -> bool;
+}
 
-            // If true parse
-            line = iter.next().unwrap();
-            let (_, if_true_str) = line.split_once("monkey ").unwrap();
-            monkey.if_true = if_true_str.parse().ok().unwrap();
+/// Worry levels modulo `modulus`
+struct Modular {
+    modulus: u64,
+}
 
-            // If false parse
-            line = iter.next().unwrap();
-            let (_, if_false_str) = line.split_once("monkey ").unwrap();
-            monkey.if_false = if_false_str.parse().ok().unwrap();
+impl Arithmetic for Modular {
+    type Level = u64;
 
-            monkeys.push(monkey)
+    fn level(&self, n: u64) -> u64 {
+        n % self.modulus
+    }
+
+    fn apply(&self, op: Op, a: u64, b: u64, monkey: usize) -> Result<u64, SimError> {
+        let m = self.modulus as u128;
+        match op {
+            Op::Add => Ok(((a as u128 + b as u128) % m) as u64),
+            Op::Sub => Ok(((a as u128 + m - b as u128) % m) as u64),
+            Op::Mul => Ok(mul_mod(a, b, self.modulus)),
+            Op::Div => Err(SimError::ModularDivision { monkey }),
         }
     }
-    monkeys
+
+    fn relieve(&self, level: u64) -> u64 {
+        level
+    }
+
+    fn is_divisible(&self, level: &u64, n: u64) -> bool {
+        level.is_multiple_of(&n)
+    }
 }
 
-fn part1(input: &InputT) {
-    let mut monkeys: Vec<Monkey<u32>> = create_monkey_list(input);
+/// Worry levels in 64 bits, divided by `divisor` after every inspection
+struct Divided {
+    divisor: u64,
+}
 
-    for _ in 0..20 {
+impl Arithmetic for Divided {
+    type Level = u64;
+
+    fn level(&self, n: u64) -> u64 {
+        n
+    }
+
+    fn apply(&self, op: Op, a: u64, b: u64, monkey: usize) -> Result<u64, SimError> {
+        let level = match op {
+            Op::Add => a.checked_add(b),
+            Op::Sub => a.checked_sub(b),
+            Op::Mul => a.checked_mul(b),
+            Op::Div if b == 0 => return Err(SimError::DivisionByZero { monkey }),
+            Op::Div => Some(a / b),
+        };
+        level.ok_or(SimError::LevelOverflow { monkey })
+    }
+
+    fn relieve(&self, level: u64) -> u64 {
+        level / self.divisor
+    }
+
+    fn is_divisible(&self, level: &u64, n: u64) -> bool {
+        level.is_multiple_of(&n)
+    }
+}
+
+/// Exact worry levels, divided by `divisor` if any
+struct Exact {
+    divisor: Option<BigInt>,
+}
+
+impl Arithmetic for Exact {
+    type Level = BigInt;
+
+    fn level(&self, n: u64) -> BigInt {
+        BigInt::from(n)
+    }
+
+    fn apply(&self, op: Op, a: BigInt, b: BigInt, monkey: usize) -> Result<BigInt, SimError> {
+        match op {
+            Op::Add => Ok(a + b),
+            Op::Sub => Ok(a - b),
+            Op::Mul => Ok(a * b),
+            Op::Div if b.is_zero() => Err(SimError::DivisionByZero { monkey }),
+            Op::Div => Ok(a.div_floor(&b)),
+        }
+    }
+
+    fn relieve(&self, level: BigInt) -> BigInt {
+        match &self.divisor {
+            Some(divisor) => level.div_floor(divisor),
+            None => level,
+        }
+    }
+
+    fn is_divisible(&self, level: &BigInt, n: u64) -> bool {
+        level.is_multiple_of(&BigInt::from(n))
+    }
+}
+
+/// Plays the rounds, returning the monkeys with their inspections
+fn throw_items<A: Arithmetic>(
+    monkeys: &[Monkey<u64>],
+    arithmetic: &A,
+    rounds: usize,
+) -> Result<Vec<Monkey<A::Level>>, SimError> {
+    let mut monkeys: Vec<Monkey<A::Level>> = monkeys
+        .iter()
+        .map(|m| m.map_items(|item| arithmetic.level(item)))
+        .collect();
+    let constant = |n| arithmetic.level(n);
+
+    for _ in 0..rounds {
         for i in 0..monkeys.len() {
-            while !monkeys.get(i).unwrap().items.is_empty() {
-                let mut item = monkeys.get_mut(i).unwrap().items.remove(0);
-
-                {
-                    let monkey = monkeys.get(i).unwrap();
-                    item = match monkey.op.0 {
-                        '+' => item + monkey.op.1 as u32,
-                        '*' => item * monkey.op.1 as u32,
-                        '/' => item / monkey.op.1 as u32,
-                        '^' => item * item,
-                        _ => 0,
-                    };
-
-                    item /= 3;
-                }
-                let test_div = monkeys.get(i).unwrap().test_div;
-                let if_true = monkeys.get(i).unwrap().if_true;
-                let if_false = monkeys.get(i).unwrap().if_false;
+            while let Some(item) = monkeys[i].items.pop_front() {
+                monkeys[i].inspections += 1;
+                let monkey = &monkeys[i];
+                let apply = |op, a, b| arithmetic.apply(op, a, b, i);
+                let item = arithmetic.relieve(monkey.op.eval(&item, &constant, &apply)?);
 
-                if item % test_div == 0 {
-                    monkeys.get_mut(if_true).unwrap().items.push(item);
+                let target = if arithmetic.is_divisible(&item, monkey.test_div) {
+                    monkey.if_true
                 } else {
-                    monkeys.get_mut(if_false).unwrap().items.push(item);
+                    monkey.if_false
+                };
+                monkeys[target].items.push_back(item);
+            }
+        }
+    }
+    Ok(monkeys)
+}
+
+/// Number of inspections of every monkey after `rounds`
+fn simulate(monkeys: &[Monkey<u64>], relief: Relief, rounds: usize) -> Result<Vec<u64>, SimError> {
+    fn inspections<T>(monkeys: Vec<Monkey<T>>) -> Vec<u64> {
+        monkeys.iter().map(|m| m.inspections).collect()
+    }
+    match relief {
+        Relief::Modulo => {
+            let modulus =
+                checked_lcm(monkeys.iter().map(|m| m.test_div)).ok_or(SimError::ModulusOverflow)?;
+            throw_items(monkeys, &Modular { modulus }, rounds).map(inspections)
+        }
+        Relief::Divide(n) => {
+            assert!(n > 0, "Relief can't divide by 0");
+            match throw_items(monkeys, &Divided { divisor: n }, rounds) {
+                Err(SimError::LevelOverflow { .. }) => {
+                    let divisor = Some(BigInt::from(n));
+                    throw_items(monkeys, &Exact { divisor }, rounds).map(inspections)
                 }
-                monkeys.get_mut(i).unwrap().inspections += 1;
+                result => result.map(inspections),
             }
         }
+        Relief::None => throw_items(monkeys, &Exact { divisor: None }, rounds).map(inspections),
     }
+}
 
-    let mut inspections = Vec::from_iter(monkeys.iter().map(|mon| mon.inspections));
+/// Product of the two largest numbers of inspections
+fn monkey_business(inspections: &[u64]) -> u64 {
+    let mut inspections = inspections.to_vec();
     inspections.sort_by(|a, b| b.cmp(a));
-    println!("{}", inspections[0] * inspections[1]);
+    inspections.iter().take(2).product()
 }
 
-fn part2(input: &InputT) {
-    let mut monkeys: Vec<Monkey<u64>> = create_monkey_list(input);
+fn business(monkeys: &[Monkey<u64>], relief: Relief, rounds: usize) -> Result<u64, SimError> {
+    simulate(monkeys, relief, rounds).map(|inspections| monkey_business(&inspections))
+}
 
-    #[cfg(debug_assertions)]
-    let mut largest_num: u64 = 1;
-    #[cfg(debug_assertions)]
-    for monkey in monkeys.iter() {
-        largest_num *= monkey.test_div as u64;
+fn business_with_relief(input: &InputT) -> u64 {
+    let monkeys = parse_monkeys(input).unwrap_or_else(|e| panic!("{e}"));
+    business(&monkeys, Relief::Divide(3), 20).unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn business_without_relief(input: &InputT) -> u64 {
+    let monkeys = parse_monkeys(input).unwrap_or_else(|e| panic!("{e}"));
+    business(&monkeys, Relief::Modulo, 10000).unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn part1(monkeys: &[Monkey<u64>]) {
+    match business(monkeys, Relief::Divide(3), 20) {
+        Ok(business) => println!("{business}"),
+        Err(e) => eprintln!("{e}"),
     }
+}
 
-    #[cfg(not(debug_assertions))]
-    const largest_num: u64 = 2 * 3 * 5 * 7 * 11 * 13 * 17 * 19; // <-- release only
+fn part2(monkeys: &[Monkey<u64>]) {
+    match business(monkeys, Relief::Modulo, 10000) {
+        Ok(business) => println!("{business}"),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+/// Reference for `business_with_relief` using big integers, so the worry
+/// levels are never truncated.
+fn reference_business_with_relief(input: &InputT) -> u64 {
+    let monkeys = parse_monkeys(input).unwrap_or_else(|e| panic!("{e}"));
+    let mut monkeys: Vec<Monkey<BigUint>> =
+        monkeys.iter().map(|m| m.map_items(BigUint::from)).collect();
+
+    for _ in 0..20 {
+        for i in 0..monkeys.len() {
+            let items = std::mem::take(&mut monkeys[i].items);
+            monkeys[i].inspections += items.len() as u64;
+
+            for item in items {
+                let monkey = &monkeys[i];
+                let apply = |op, a: BigUint, b: BigUint| -> Result<BigUint, ()> {
+                    Ok(match op {
+                        Op::Add => a + b,
+                        Op::Sub => a - b,
+                        Op::Mul => a * b,
+                        Op::Div => a / b,
+                    })
+                };
+                let item = monkey.op.eval(&item, &BigUint::from, &apply).unwrap() / 3u32;
+
+                let target = if (&item % monkey.test_div) == BigUint::from(0u32) {
+                    monkey.if_true
+                } else {
+                    monkey.if_false
+                };
+                monkeys[target].items.push_back(item);
+            }
+        }
+    }
+    let inspections: Vec<u64> = monkeys.iter().map(|m| m.inspections).collect();
+    monkey_business(&inspections)
+}
+
+/// Reference for `business_without_relief`. Every item keeps its worry level
+/// modulo each monkey's divisor separately, which is all the divisibility
+/// tests need, instead of relying on a common multiple.
+fn reference_business_without_relief(input: &InputT) -> u64 {
+    let mut monkeys = parse_monkeys(input).unwrap_or_else(|e| panic!("{e}"));
+    let divisors: Vec<u64> = monkeys.iter().map(|m| m.test_div).collect();
+    let mut items: Vec<Vec<Vec<u64>>> = monkeys
+        .iter()
+        .map(|m| {
+            m.items
+                .iter()
+                .map(|&item| divisors.iter().map(|d| item % d).collect())
+                .collect()
+        })
+        .collect();
 
     for _ in 0..10000 {
         for i in 0..monkeys.len() {
-            while !monkeys.get(i).unwrap().items.is_empty() {
-                let mut item = monkeys.get_mut(i).unwrap().items.remove(0);
-
-                {
-                    let monkey = monkeys.get(i).unwrap();
-                    item = match monkey.op.0 {
-                        '+' => item + monkey.op.1 as u64,
-                        '*' => item * monkey.op.1 as u64,
-                        '^' => item * item,
-                        _ => 0,
-                    };
-
-                    item = item % largest_num;
-                }
-                let test_div = monkeys.get(i).unwrap().test_div;
-                let if_true = monkeys.get(i).unwrap().if_true;
-                let if_false = monkeys.get(i).unwrap().if_false;
+            let held = std::mem::take(&mut items[i]);
+            monkeys[i].inspections += held.len() as u64;
 
-                if item % test_div as u64 == 0 {
-                    monkeys.get_mut(if_true).unwrap().items.push(item);
+            for residues in held {
+                let monkey = &monkeys[i];
+                let residues: Vec<u64> = residues
+                    .iter()
+                    .zip(divisors.iter())
+                    .map(|(&r, &d)| {
+                        let apply = |op, a: u64, b: u64| match op {
+                            Op::Add => Ok((a + b) % d),
+                            Op::Sub => Ok((a + d - b) % d),
+                            Op::Mul => Ok((a * b) % d),
+                            Op::Div => Err(()),
+                        };
+                        let constant = |n| n % d;
+                        monkey
+                            .op
+                            .eval(&r, &constant, &apply)
+                            .expect("Can't divide residues")
+                    })
+                    .collect();
+
+                let target = if residues[i] == 0 {
+                    monkey.if_true
                 } else {
-                    monkeys.get_mut(if_false).unwrap().items.push(item);
-                }
-                monkeys.get_mut(i).unwrap().inspections += 1;
+                    monkey.if_false
+                };
+                items[target].push(residues);
             }
         }
     }
+    let inspections: Vec<u64> = monkeys.iter().map(|m| m.inspections).collect();
+    monkey_business(&inspections)
+}
 
-    let mut inspections = Vec::from_iter(monkeys.iter().map(|mon| mon.inspections));
-    // println!("{:?}", inspections);
-    inspections.sort_by(|a, b| b.cmp(a));
-    println!("{}", inspections[0] * inspections[1]);
+fn check() -> usize {
+    let mut mismatches = aoc::gen::compare(
+        11,
+        1,
+        0..20,
+        business_with_relief,
+        reference_business_with_relief,
+    );
+    mismatches += aoc::gen::compare(
+        11,
+        2,
+        0..20,
+        business_without_relief,
+        reference_business_without_relief,
+    );
+    mismatches
+}
+
+/// Relief and rounds given by `--relief` and `--rounds`
+fn custom_flags(relief: Option<&str>, rounds: Option<&str>) -> Result<(Relief, usize), String> {
+    let relief = match relief {
+        None => Relief::Divide(3),
+        Some("lcm") => Relief::Modulo,
+        Some("none") => Relief::None,
+        Some(n) => match n.parse() {
+            Ok(n) if n > 0 => Relief::Divide(n),
+            _ => return Err("`--relief` expects `lcm`, `none` or a number above 0".to_string()),
+        },
+    };
+    let rounds = match rounds {
+        None => 20,
+        Some(n) => n
+            .parse()
+            .map_err(|_| "`--rounds` expects a number of rounds")?,
+    };
+    Ok((relief, rounds))
+}
+
+/// Runs the rounds and relief given by `--rounds` and `--relief`
+fn custom_run(monkeys: &[Monkey<u64>], relief: Option<String>, rounds: Option<String>) {
+    let (relief, rounds) = match custom_flags(relief.as_deref(), rounds.as_deref()) {
+        Ok(flags) => flags,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    match simulate(monkeys, relief, rounds) {
+        Ok(inspections) => {
+            println!(
+                "\nMonkey business after {rounds} rounds ({relief}): {}",
+                monkey_business(&inspections)
+            );
+            for (i, n) in inspections.iter().enumerate() {
+                println!("Monkey {i} inspected items {n} times.");
+            }
+        }
+        Err(e) => eprintln!("{e}"),
+    }
 }
 
 fn main() {
@@ -169,10 +816,199 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
+
+    if has_flag("--check") {
+        std::process::exit(check().min(1) as i32);
+    }
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input)
+    if has_flag("--reference") {
+        run_part("PART 1 (reference)", || println!("{}", reference_business_with_relief(&input)));
+        run_part("PART 2 (reference)", || {
+            println!("{}", reference_business_without_relief(&input))
+        });
+        return;
+    }
+    let monkeys = match parse_monkeys(&input) {
+        Ok(monkeys) => monkeys,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    run_part("PART 1", || part1(&monkeys));
+    run_part("PART 2", || part2(&monkeys));
+
+    let (relief, rounds) = (flag_value("--relief"), flag_value("--rounds"));
+    if relief.is_some() || rounds.is_some() {
+        custom_run(&monkeys, relief, rounds);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn example() -> Vec<Monkey<u64>> {
+        let input = read_inputs("data/examples/11.txt").unwrap();
+        parse_monkeys(&input).unwrap()
+    }
+
+    #[test]
+    fn expressions() {
+        let eval = |text: &str, old: i64| {
+            let apply = |op, a: i64, b: i64| -> Result<i64, ()> {
+                Ok(match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div => a / b,
+                })
+            };
+            Expr::parse(text)
+                .unwrap()
+                .eval(&old, &|n| n as i64, &apply)
+                .unwrap()
+        };
+        assert_eq!(eval("old * old", 7), 49);
+        assert_eq!(eval("old + old", 7), 14);
+        assert_eq!(eval("2 + old * 3 - 1", 4), 13);
+        assert_eq!(eval("(2 + old) * (3 - 1)", 4), 12);
+        assert_eq!(eval("old - 2 - 3", 10), 5);
+        assert_eq!(eval("old / 2 / 2", 20), 5);
+
+        let show = |text: &str| Expr::parse(text).unwrap().to_string();
+        assert_eq!(show("(old*19)"), "old * 19");
+        assert_eq!(show("(old + 1) * old"), "(old + 1) * old");
+        assert_eq!(show("old - (old - 1)"), "old - (old - 1)");
+        assert_eq!(show("(old - old) - 1"), "old - old - 1");
+
+        let error = |text: &str| Expr::parse(text).unwrap_err();
+        assert_eq!(error("old ^ 2"), "unexpected `^`");
+        assert_eq!(error("new + 1"), "unknown term `new`");
+        assert_eq!(
+            error("old *"),
+            "expected `old`, a number or `(`, found the end"
+        );
+        assert_eq!(error("(old + 1"), "expected `)`, found the end");
+        assert_eq!(error("old 1"), "unexpected `1`");
+    }
+
+    #[test]
+    fn parsing() {
+        let monkeys = example();
+        assert_eq!(monkeys.len(), 4);
+        assert_eq!(monkeys[2].items, [79, 60, 97]);
+        assert_eq!(monkeys[2].op.to_string(), "old * old");
+        assert_eq!(
+            (monkeys[2].test_div, monkeys[2].if_true, monkeys[2].if_false),
+            (13, 1, 3)
+        );
+
+        let error = |replace: &str, by: &str| {
+            let input = std::fs::read_to_string("data/examples/11.txt").unwrap();
+            let input: Vec<String> = input
+                .replacen(replace, by, 1)
+                .lines()
+                .map(String::from)
+                .collect();
+            parse_monkeys(&input).unwrap_err().to_string()
+        };
+        assert_eq!(
+            error("old * 19", "old ** 19"),
+            "Invalid operation on line 3: expected `old`, a number or `(`, found `*`"
+        );
+        assert_eq!(
+            error("by 23", "by 0"),
+            "Invalid line 4 `Test: divisible by 0`, expected `Test: divisible by N, with N > 0`"
+        );
+        assert_eq!(
+            error("79, 98", "79, x"),
+            "Invalid line 2 `Starting items: 79, x`, expected `Starting items: N, ...`"
+        );
+        assert_eq!(error("monkey 2", "monkey 0"), "Monkey 0 throws to itself");
+        assert_eq!(
+            error("monkey 3", "monkey 4"),
+            "Monkey 0 throws to monkey 4, which doesn't exist"
+        );
+        assert_eq!(
+            error("Monkey 1:", "Monkey 2:"),
+            "Invalid line 8 `Monkey 2:`, expected `Monkey N:`"
+        );
+        assert_eq!(parse_monkeys(&[]).unwrap_err(), ParseError::NoMonkeys);
+    }
+
+    #[test]
+    fn relief_strategies() {
+        let monkeys = example();
+        assert_eq!(
+            simulate(&monkeys, Relief::Divide(3), 20).unwrap(),
+            [101, 95, 7, 105]
+        );
+        assert_eq!(business(&monkeys, Relief::Divide(3), 20), Ok(10605));
+        assert_eq!(
+            simulate(&monkeys, Relief::Modulo, 20).unwrap(),
+            [99, 97, 8, 103]
+        );
+        assert_eq!(business(&monkeys, Relief::Modulo, 10000), Ok(2713310158));
+        // Without relief, the exact levels give the same inspections as modulo
+        assert_eq!(
+            simulate(&monkeys, Relief::None, 20),
+            simulate(&monkeys, Relief::Modulo, 20)
+        );
+
+        let mut halving = monkeys.clone();
+        halving[1].op = Expr::parse("old / 2").unwrap();
+        assert_eq!(
+            business(&halving, Relief::Modulo, 1),
+            Err(SimError::ModularDivision { monkey: 1 })
+        );
+        halving[1].op = Expr::parse("old / (old - old)").unwrap();
+        assert_eq!(
+            business(&halving, Relief::None, 1),
+            Err(SimError::DivisionByZero { monkey: 1 })
+        );
+        assert_eq!(
+            business(&halving, Relief::Divide(3), 1),
+            Err(SimError::DivisionByZero { monkey: 1 })
+        );
+        // Levels that don't fit in 64 bits are computed exactly
+        halving[1].op = Expr::parse("old - 100").unwrap();
+        assert_eq!(
+            throw_items(&halving, &Divided { divisor: 3 }, 1).unwrap_err(),
+            SimError::LevelOverflow { monkey: 1 }
+        );
+        assert_eq!(
+            simulate(&halving, Relief::Divide(3), 1).unwrap(),
+            [2, 4, 3, 5]
+        );
+    }
+
+    #[test]
+    fn custom_flags_errors() {
+        assert_eq!(custom_flags(None, None), Ok((Relief::Divide(3), 20)));
+        assert_eq!(
+            custom_flags(Some("lcm"), Some("100")),
+            Ok((Relief::Modulo, 100))
+        );
+        assert_eq!(
+            custom_flags(Some("0"), None).unwrap_err(),
+            "`--relief` expects `lcm`, `none` or a number above 0"
+        );
+        assert_eq!(
+            custom_flags(None, Some("many")).unwrap_err(),
+            "`--rounds` expects a number of rounds"
+        );
+    }
+
+    #[test]
+    fn generated_monkeys() {
+        for seed in 0..10 {
+            let input = aoc::gen::generate(11, seed, aoc::gen::default_size(11)).unwrap();
+            let monkeys = parse_monkeys(&input).unwrap();
+            let divided = throw_items(&monkeys, &Divided { divisor: 3 }, 20);
+            assert!(divided.is_ok(), "seed {seed}");
+        }
+    }
 }
@@ -1,7 +1,7 @@
 use std::cmp::Ordering;
 use std::str::Split;
 
-use aoc::read_inputs;
+use aoc::{read_inputs, run_part};
 
 type InputT = Vec<String>;
 
@@ -165,8 +165,6 @@ fn main() {
     let file_path = format!("data/{:02}.txt", day);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
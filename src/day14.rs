@@ -1,5 +1,7 @@
-use aoc::read_inputs;
+use aoc::sim::{debug, Simulation};
+use aoc::{has_flag, input_arg, read_inputs, run_part};
 use std::cmp::{max, min};
+use std::fmt;
 
 type InputT = Vec<String>;
 
@@ -14,21 +16,36 @@ struct Grid {
     grid: GridT,
 }
 
-impl Grid {
-    fn print_grid(&self) {
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use Sediment::*;
-        for (i, elem) in self.grid.iter().enumerate() {
-            if i % self.size[0] == 0 && i > 0 {
-                println!();
-            }
 
-            match elem {
-                Air => print!("."),
-                Rock => print!("#"),
-                Sand => print!("o"),
+        // Only draw the columns with something in them, the floor of part 2
+        // is much wider than the pile of sand
+        let last = self.size[1] - 1;
+        let has_floor = (0..self.size[0]).all(|x| self.get(x, last) == Some(&Rock));
+        let rows = if has_floor { last } else { self.size[1] };
+        let used = |x: usize| (0..rows).any(|y| self.get(x, y) != Some(&Air));
+        let x1 = (0..self.size[0]).find(|&x| used(x)).unwrap_or(0);
+        let x2 = (0..self.size[0]).rev().find(|&x| used(x)).unwrap_or(0);
+
+        for y in 0..self.size[1] {
+            for x in x1..=x2 {
+                match self.get(x, y).unwrap() {
+                    Air => write!(f, ".")?,
+                    Rock => write!(f, "#")?,
+                    Sand => write!(f, "o")?,
+                }
             }
+            writeln!(f)?;
         }
-        println!();
+        Ok(())
+    }
+}
+
+impl Grid {
+    fn print_grid(&self) {
+        print!("{self}");
     }
 
     fn add_sand_unit(&mut self) -> Option<[usize; 2]> {
@@ -271,6 +288,58 @@ fn part2(input: &InputT) {
     println!("{}", i);
 }
 
+/// Drops the sand one unit at a time for the debugger
+struct SandSim {
+    grid: Grid,
+    units: usize,
+    last: Option<[usize; 2]>,
+    done: bool,
+}
+
+impl SandSim {
+    fn new(grid: Grid) -> Self {
+        Self {
+            grid,
+            units: 0,
+            last: None,
+            done: false,
+        }
+    }
+}
+
+impl Simulation for SandSim {
+    fn step(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+        match self.grid.add_sand_unit() {
+            Some(sand) => {
+                self.units += 1;
+                self.last = Some(sand);
+                self.done = sand == [START_SAND[0] - self.grid.leftedge, START_SAND[1]];
+                true
+            }
+            None => {
+                self.done = true;
+                false
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        self.grid.to_string()
+    }
+
+    fn state(&self) -> Vec<(&'static str, i64)> {
+        let [x, y] = self.last.unwrap_or([START_SAND[0] - self.grid.leftedge, START_SAND[1]]);
+        vec![
+            ("sand", self.units as i64),
+            ("x", (x + self.grid.leftedge) as i64),
+            ("y", y as i64),
+        ]
+    }
+}
+
 fn main() {
     let day: u32 = 14;
     #[cfg(debug_assertions)]
@@ -278,10 +347,17 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    if has_flag("--debug") {
+        if has_flag("--part2") {
+            debug(SandSim::new(create_grid2(&input)));
+        } else {
+            debug(SandSim::new(create_grid(&input)));
+        }
+        return;
+    }
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
@@ -1,4 +1,4 @@
-use aoc::read_inputs;
+use aoc::{input_arg, read_inputs, run_part};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::cmp::{max, min};
@@ -167,10 +167,9 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
@@ -1,24 +1,31 @@
 use std::collections::HashSet;
 
-use aoc::read_inputs;
+use aoc::sim::{debug, Simulation};
+use aoc::{has_flag, input_arg, read_inputs, run_part};
 
 type InputT = Vec<String>;
 
-#[allow(dead_code)]
-fn print_rock(rock: &HashSet<(i64, i64)>, grid: &HashSet<(i64, i64)>) {
+fn draw_rock(rock: &HashSet<(i64, i64)>, grid: &HashSet<(i64, i64)>, bottom: i64) -> String {
     let max_y = rock.iter().map(|&(_, y)| y).max().unwrap();
-    for y in (0..=max_y).rev() {
+    let mut out = String::new();
+    for y in (bottom..=max_y).rev() {
         for x in 0..7 {
             if rock.contains(&(x, y)) {
-                print!("@");
+                out.push('@');
             } else if grid.contains(&(x, y)) {
-                print!("#")
+                out.push('#');
             } else {
-                print!(".");
+                out.push('.');
             }
         }
-        println!();
+        out.push('\n');
     }
+    out
+}
+
+#[allow(dead_code)]
+fn print_rock(rock: &HashSet<(i64, i64)>, grid: &HashSet<(i64, i64)>) {
+    print!("{}", draw_rock(rock, grid, 0));
 }
 
 fn side_mv(grid: &HashSet<(i64, i64)>, jet: char, rock: &mut HashSet<(i64, i64)>) {
@@ -73,9 +80,9 @@ fn offset_rock(rock: &mut HashSet<(i64, i64)>, x_off: i64, y_off: i64) {
     *rock = new_rock;
 }
 
-fn part1(input: &InputT) {
+fn shapes() -> [HashSet<(i64, i64)>; 5] {
     // Shapes order = _, +, L,, I, []
-    let shapes: [HashSet<(i64, i64)>; 5] = [
+    [
         vec![(0, 0), (1, 0), (2, 0), (3, 0)].into_iter().collect(),
         vec![(1, 0), (0, 1), (2, 1), (1, 2)].into_iter().collect(),
         vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)]
@@ -83,11 +90,13 @@ fn part1(input: &InputT) {
             .collect(),
         vec![(0, 0), (0, 1), (0, 2), (0, 3)].into_iter().collect(),
         vec![(0, 0), (1, 0), (0, 1), (1, 1)].into_iter().collect(),
-    ];
-    const END: usize = 2022;
+    ]
+}
+
+fn simulated_height(jets: &str, end: usize) -> i64 {
+    let shapes = shapes();
     const NSHAPES: usize = 5;
 
-    let jets = &input[0];
     let jets_sz = jets.len();
 
     let mut stopped_rocks = 0;
@@ -98,7 +107,7 @@ fn part1(input: &InputT) {
     let mut i = 0;
     let mut highest_point = 0;
 
-    while stopped_rocks < END {
+    while stopped_rocks < end {
         let jet = jets.chars().nth(i % jets_sz).unwrap();
         side_mv(&grid, jet, &mut rock);
 
@@ -114,7 +123,11 @@ fn part1(input: &InputT) {
         }
         i += 1;
     }
-    println!("{highest_point}");
+    highest_point
+}
+
+fn part1(input: &InputT) {
+    println!("{}", simulated_height(&input[0], 2022));
 }
 
 fn are_silces_eq(s1: &[i64], s2: &[i64]) -> bool {
@@ -134,21 +147,10 @@ fn are_silces_eq(s1: &[i64], s2: &[i64]) -> bool {
     true
 }
 
-fn part2(input: &InputT) {
-    // Shapes order = _, +, L,, I, []
-    let shapes: [HashSet<(i64, i64)>; 5] = [
-        vec![(0, 0), (1, 0), (2, 0), (3, 0)].into_iter().collect(),
-        vec![(1, 0), (0, 1), (2, 1), (1, 2)].into_iter().collect(),
-        vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)]
-            .into_iter()
-            .collect(),
-        vec![(0, 0), (0, 1), (0, 2), (0, 3)].into_iter().collect(),
-        vec![(0, 0), (1, 0), (0, 1), (1, 1)].into_iter().collect(),
-    ];
+fn extrapolated_height(jets: &str, end: usize) -> i64 {
+    let shapes = shapes();
     const NSHAPES: usize = 5;
-    const END: usize = 1000000000000;
 
-    let jets = &input[0];
     let jets_sz = jets.len();
 
     let mut stopped_rocks = 0;
@@ -159,10 +161,13 @@ fn part2(input: &InputT) {
     let mut i = 0;
     let mut highest_point = 0;
     let mut dh = vec![];
+    // Next shape, next jet and surface after every number of rocks, as in
+    // `reference_height`. Equal height changes alone aren't a cycle.
+    let mut states = vec![(0, 0, surface(&grid, 0))];
     let mut extra = 0;
     let mut repeat_len = 0;
 
-    'outer: while stopped_rocks < END {
+    'outer: while stopped_rocks < end {
         let jet = jets.chars().nth(i % jets_sz).unwrap();
         side_mv(&grid, jet, &mut rock);
 
@@ -175,12 +180,15 @@ fn part2(input: &InputT) {
             highest_point = h2;
             rock = shapes[stopped_rocks % NSHAPES].clone();
             offset_rock(&mut rock, 2, highest_point + 3);
+            let next_jet = (i + 1) % jets_sz;
+            states.push((stopped_rocks % NSHAPES, next_jet, surface(&grid, highest_point)));
         }
 
         let max_slice_len = dh.len() / 3;
         for k in 0..max_slice_len {
             let hsize = (dh.len() - k) / 2;
-            if are_silces_eq(&dh[k..k+hsize], &dh[k+hsize..]) {
+            let same_state = states[k] == states[k + hsize];
+            if same_state && are_silces_eq(&dh[k..k+hsize], &dh[k+hsize..]) {
                 repeat_len = hsize;
                 extra = k;
                 break 'outer ;
@@ -189,16 +197,172 @@ fn part2(input: &InputT) {
         i += 1;
     }
 
-    let repeat = ((END - extra) / repeat_len) as i64;
-    let mod_extra = (END - extra) % repeat_len;
+    if repeat_len == 0 {
+        // `end` was reached before a cycle was found
+        return highest_point;
+    }
+
+    let repeat = ((end - extra) / repeat_len) as i64;
+    let mod_extra = (end - extra) % repeat_len;
 
     let initial_height = dh[..extra].iter().sum::<i64>();
     let repeated_height = dh[extra..extra+repeat_len].iter().sum::<i64>() * repeat;
     let mod_height = dh[extra..extra+mod_extra].iter().sum::<i64>();
 
-    highest_point = initial_height + repeated_height + mod_height;
+    initial_height + repeated_height + mod_height
+}
+
+fn part2(input: &InputT) {
+    println!("{}", extrapolated_height(&input[0], 1_000_000_000_000));
+}
+
+/// Air cells reachable from above the tower when moving only left, right and
+/// down, relative to the tower height. A falling rock can only ever occupy
+/// these cells and every cell it bumps into outside of them is blocked, so two
+/// towers with the same surface (and the same next shape and jet) grow the
+/// exact same way from there on.
+fn surface(grid: &HashSet<(i64, i64)>, height: i64) -> Vec<(i64, i64)> {
+    let mut seen: HashSet<(i64, i64)> = (0..7).map(|x| (x, height)).collect();
+    let mut stack: Vec<(i64, i64)> = seen.iter().copied().collect();
+
+    while let Some((x, y)) = stack.pop() {
+        for next in [(x - 1, y), (x + 1, y), (x, y - 1)] {
+            let is_air = (0..7).contains(&next.0) && next.1 >= 0 && !grid.contains(&next);
+            if is_air && seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
 
-    println!("{highest_point}");
+    let mut surface: Vec<(i64, i64)> = seen
+        .into_iter()
+        .map(|(x, y)| (x, height - y))
+        .collect();
+    surface.sort();
+    surface
+}
+
+/// Reference for `extrapolated_height`. Drops the rocks one by one and only
+/// skips ahead once the whole state (next shape, next jet and `surface`)
+/// repeats exactly.
+fn reference_height(jets: &str, end: usize) -> i64 {
+    let shapes = shapes();
+    let jets: Vec<char> = jets.chars().collect();
+
+    let mut grid: HashSet<(i64, i64)> = HashSet::new();
+    let mut seen = std::collections::HashMap::new();
+    let mut heights = vec![0]; // height after n rocks
+    let mut jet = 0;
+
+    for n in 0..end {
+        let height = heights[n];
+        let mut rock = shapes[n % shapes.len()].clone();
+        offset_rock(&mut rock, 2, height + 3);
+
+        loop {
+            side_mv(&grid, jets[jet], &mut rock);
+            jet = (jet + 1) % jets.len();
+            if !down_mv(&grid, &mut rock) {
+                break;
+            }
+        }
+        let top = rock.iter().map(|&(_, y)| y + 1).max().unwrap();
+        grid.extend(rock);
+        heights.push(height.max(top));
+
+        let rocks = n + 1;
+        let key = (rocks % shapes.len(), jet, surface(&grid, heights[rocks]));
+        if let Some(&prev) = seen.get(&key) {
+            let period = rocks - prev;
+            let cycles = ((end - rocks) / period) as i64;
+            let rest = (end - rocks) % period;
+            let cycle_height = heights[rocks] - heights[prev];
+            return heights[rocks] + cycles * cycle_height + heights[prev + rest] - heights[prev];
+        }
+        seen.insert(key, rocks);
+    }
+    heights[end]
+}
+
+fn check() -> usize {
+    let mut mismatches = aoc::gen::compare(
+        17,
+        1,
+        0..20,
+        |input| extrapolated_height(&input[0], 2022),
+        |input| simulated_height(&input[0], 2022),
+    );
+    mismatches += aoc::gen::compare(
+        17,
+        2,
+        0..20,
+        |input| extrapolated_height(&input[0], 1_000_000_000_000),
+        |input| reference_height(&input[0], 1_000_000_000_000),
+    );
+    mismatches
+}
+
+/// Pushes and drops the rocks one jet at a time for the debugger
+struct RockSim {
+    jets: Vec<char>,
+    end: usize,
+    tick: usize,
+    grid: HashSet<(i64, i64)>,
+    rock: HashSet<(i64, i64)>,
+    stopped_rocks: usize,
+    highest_point: i64,
+}
+
+impl RockSim {
+    fn new(jets: &str, end: usize) -> Self {
+        let mut rock = shapes()[0].clone();
+        offset_rock(&mut rock, 2, 3);
+        Self {
+            jets: jets.chars().collect(),
+            end,
+            tick: 0,
+            grid: HashSet::new(),
+            rock,
+            stopped_rocks: 0,
+            highest_point: 0,
+        }
+    }
+}
+
+impl Simulation for RockSim {
+    fn step(&mut self) -> bool {
+        if self.stopped_rocks >= self.end {
+            return false;
+        }
+
+        let jet = self.jets[self.tick % self.jets.len()];
+        side_mv(&self.grid, jet, &mut self.rock);
+
+        if !down_mv(&self.grid, &mut self.rock) {
+            self.stopped_rocks += 1;
+            self.grid.extend(&self.rock);
+            let rock_top = self.rock.iter().map(|&(_, y)| y + 1).max().unwrap();
+            self.highest_point = self.highest_point.max(rock_top);
+            self.rock = shapes()[self.stopped_rocks % 5].clone();
+            offset_rock(&mut self.rock, 2, self.highest_point + 3);
+        }
+        self.tick += 1;
+        true
+    }
+
+    fn render(&self) -> String {
+        // The top of the tower is all that matters
+        draw_rock(&self.rock, &self.grid, (self.highest_point - 20).max(0))
+    }
+
+    fn state(&self) -> Vec<(&'static str, i64)> {
+        vec![
+            ("rocks", self.stopped_rocks as i64),
+            ("height", self.highest_point),
+            ("jet", (self.tick % self.jets.len()) as i64),
+            ("shape", (self.stopped_rocks % 5) as i64),
+        ]
+    }
 }
 
 fn main() {
@@ -208,10 +372,25 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
+
+    if has_flag("--check") {
+        std::process::exit(check().min(1) as i32);
+    }
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input)
+    if has_flag("--debug") {
+        let end = if has_flag("--part2") { 1_000_000_000_000 } else { 2022 };
+        debug(RockSim::new(&input[0], end));
+        return;
+    }
+    if has_flag("--reference") {
+        run_part("PART 1 (reference)", || println!("{}", simulated_height(&input[0], 2022)));
+        run_part("PART 2 (reference)", || {
+            println!("{}", reference_height(&input[0], 1_000_000_000_000))
+        });
+        return;
+    }
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
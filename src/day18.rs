@@ -1,4 +1,4 @@
-use aoc::read_inputs;
+use aoc::{read_inputs, run_part};
 use std::collections::HashSet;
 
 type InputT = Vec<String>;
@@ -120,8 +120,6 @@ fn main() {
     let file_path = format!("data/{:02}.txt", day);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
@@ -1,4 +1,4 @@
-use aoc::read_inputs;
+use aoc::{has_flag, input_arg, read_inputs, run_part};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::cmp::max;
@@ -183,47 +183,118 @@ fn dfs(blueprint: &Blueprint, resources: &[i32; 4], robots: &[i32; 4], time: i32
     max(max_geo, resources[3] + robots[3] * time)
 }
 
-fn part1(input: &InputT) {
-    let mut geodes = vec![];
+fn max_geodes(blueprint: &Blueprint, time: i32) -> i32 {
+    let resources = [0; 4]; // [ore, clay, obs geo]
+    let robots = [1, 0, 0, 0]; // number of robots [ore, clay, obs, geo]
+
+    dfs(blueprint, &resources, &robots, time)
+}
+
+/// Reference for `dfs`. Goes minute by minute trying every robot that can be
+/// afforded (or none). The only branches pruned are the ones that:
+/// - can't beat the best so far even with a new geode robot every minute,
+/// - build more robots of a kind than can be spent in a minute,
+/// - build a robot that was affordable the minute before but was `skipped`
+///   (building it a minute earlier is never worse).
+fn reference_dfs(
+    blueprint: &Blueprint,
+    resources: [i32; 4],
+    robots: [i32; 4],
+    skipped: [bool; 4],
+    time: i32,
+    best: &mut i32,
+) {
+    let geodes = resources[3] + robots[3] * time;
+    *best = max(*best, geodes);
+    if time == 0 || geodes + time * (time - 1) / 2 <= *best {
+        return;
+    }
+
+    let max_need_ore = [blueprint.ore, blueprint.clay, blueprint.obs[0], blueprint.geo[0]]
+        .into_iter()
+        .max()
+        .unwrap();
+    // [ore, clay, obsidian] cost of each robot and the most worth having
+    let robot_costs = [
+        ([blueprint.ore, 0, 0], max_need_ore),
+        ([blueprint.clay, 0, 0], blueprint.obs[1]),
+        ([blueprint.obs[0], blueprint.obs[1], 0], blueprint.geo[1]),
+        ([blueprint.geo[0], 0, blueprint.geo[1]], i32::MAX),
+    ];
+
+    let mut affordable = [false; 4];
+    for (robot, (cost, max_robots)) in robot_costs.iter().enumerate().rev() {
+        affordable[robot] = (0..3).all(|i| resources[i] >= cost[i]);
+        if !affordable[robot] || skipped[robot] || robots[robot] >= *max_robots {
+            continue;
+        }
 
-    for txt in input {
-        let blueprint = Blueprint::from_string(txt);
-        let resources = [0; 4];     // [ore, clay, obs geo]
-        let robots = [1, 0, 0, 0];  // number of robots [ore, clay, obs, geo]
+        let mut new_resources = resources;
+        for i in 0..4 {
+            new_resources[i] += robots[i] - cost.get(i).unwrap_or(&0);
+        }
+        let mut new_robots = robots;
+        new_robots[robot] += 1;
+        reference_dfs(blueprint, new_resources, new_robots, [false; 4], time - 1, best);
+    }
 
-        geodes.push(dfs(&blueprint, &resources, &robots, 24));
+    let mut new_resources = resources;
+    for i in 0..4 {
+        new_resources[i] += robots[i];
     }
+    reference_dfs(blueprint, new_resources, robots, affordable, time - 1, best);
+}
+
+fn reference_max_geodes(blueprint: &Blueprint, time: i32) -> i32 {
+    let mut best = 0;
+    reference_dfs(blueprint, [0; 4], [1, 0, 0, 0], [false; 4], time, &mut best);
+    best
+}
 
-    let quality_levels: i32 = geodes
+fn quality_levels(input: &[String], max_geodes: fn(&Blueprint, i32) -> i32) -> i32 {
+    input
         .iter()
         .enumerate()
-        .map(|(i, &x)| (i + 1) as i32 * x)
-        .sum();
-    println!("{}", quality_levels);
+        .map(|(i, txt)| (i + 1) as i32 * max_geodes(&Blueprint::from_string(txt), 24))
+        .sum()
 }
 
-fn part2(input: &InputT) {
+fn geodes_product(input: &[String], max_geodes: fn(&Blueprint, i32) -> i32) -> i32 {
     #[cfg(debug_assertions)]
     const MAX_BLUEPRINT: usize = 2;
     #[cfg(not(debug_assertions))]
     const MAX_BLUEPRINT: usize = 3;
 
-    let mut geodes = vec![];
+    input[..MAX_BLUEPRINT]
+        .iter()
+        .map(|txt| max_geodes(&Blueprint::from_string(txt), 32))
+        .product()
+}
 
-    for txt in &input[..MAX_BLUEPRINT] {
-        let blueprint = Blueprint::from_string(txt);
-        let resources = [0; 4]; // [ore, clay, obs geo]
-        let robots = [1, 0, 0, 0]; // number of robots [ore, clay, obs, geo]
+fn part1(input: &InputT) {
+    println!("{}", quality_levels(input, max_geodes));
+}
 
-        geodes.push(dfs(&blueprint, &resources, &robots, 32));
-    }
+fn part2(input: &InputT) {
+    println!("{}", geodes_product(input, max_geodes));
+}
 
-    let quality_levels: i32 = geodes
-        .iter()
-        .copied()
-        .reduce(|a, b| a * b)
-        .unwrap();
-    println!("{}", quality_levels);
+fn check() -> usize {
+    let mut mismatches = aoc::gen::compare(
+        19,
+        1,
+        0..20,
+        |input| quality_levels(input, max_geodes),
+        |input| quality_levels(input, reference_max_geodes),
+    );
+    mismatches += aoc::gen::compare(
+        19,
+        2,
+        0..20,
+        |input| geodes_product(input, max_geodes),
+        |input| geodes_product(input, reference_max_geodes),
+    );
+    mismatches
 }
 
 fn main() {
@@ -233,10 +304,22 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
+
+    if has_flag("--check") {
+        std::process::exit(check().min(1) as i32);
+    }
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    if has_flag("--reference") {
+        run_part("PART 1 (reference)", || {
+            println!("{}", quality_levels(&input, reference_max_geodes))
+        });
+        run_part("PART 2 (reference)", || {
+            println!("{}", geodes_product(&input, reference_max_geodes))
+        });
+        return;
+    }
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
@@ -1,4 +1,5 @@
-use aoc::read_inputs;
+use aoc::math::modulo;
+use aoc::{read_inputs, run_part};
 
 type InputT = Vec<String>;
 
@@ -34,11 +35,8 @@ fn part1(input: &InputT) {
         let idx = file.get_idx(i).unwrap();
         let elem = file.sequence.remove(idx);
 
-        let mut new_pos = (idx as i64 + elem.1) % (len - 1) as i64;
-        if new_pos <= 0 {
-            new_pos = len as i64 + new_pos - 1;
-        }
-        let new_pos = new_pos as usize;
+        // The list without `elem` is len - 1 long
+        let new_pos = modulo(idx as i64 + elem.1, len as i64 - 1) as usize;
         file.sequence.insert(new_pos, elem);
         // println!("{:?}", file);
     }
@@ -65,11 +63,8 @@ fn part2(input: &InputT) {
             let idx = file.get_idx(i).unwrap();
             let elem = file.sequence.remove(idx);
     
-            let mut new_pos = (idx as i64 + elem.1) % (len - 1) as i64;
-            if new_pos <= 0 {
-                new_pos = len as i64 + new_pos - 1;
-            }
-            let new_pos = new_pos as usize;
+            // The list without `elem` is len - 1 long
+            let new_pos = modulo(idx as i64 + elem.1, len as i64 - 1) as usize;
             file.sequence.insert(new_pos, elem);
             // println!("{:?}", file);
         }
@@ -94,8 +89,6 @@ fn main() {
     let file_path = format!("data/{:02}.txt", day);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
@@ -1,4 +1,5 @@
-use aoc::read_inputs;
+use aoc::{has_flag, input_arg, read_inputs, run_part};
+use num::{BigInt, BigRational, Zero};
 use std::collections::HashMap;
 
 type InputT = Vec<String>;
@@ -47,11 +48,13 @@ fn find_val(map: &HashMap<&str, Op>, name: &str) -> f64 {
     }
 }
 
-fn part1(input: &InputT) {
+fn root_val(input: &InputT) -> f64 {
     let map = create_map(input);
+    find_val(&map, "root")
+}
 
-    let root = find_val(&map, "root");
-    println!("{}", root);
+fn part1(input: &InputT) {
+    println!("{}", root_val(input));
 }
 
 fn contains_me(map: &HashMap<&str, Op>, root: &str) -> bool {
@@ -120,7 +123,7 @@ fn find_my_val(map: &HashMap<&str, Op>, name: &str, target: f64) -> f64 {
     }
 }
 
-fn part2(input: &InputT) {
+fn my_val(input: &InputT) -> f64 {
     let mut map = create_map(input);
     let root = &map["root"];
 
@@ -128,8 +131,77 @@ fn part2(input: &InputT) {
         map.insert("root", Op::Expr(*rhs, '-', *lhs));
     }
 
-    let me = find_my_val(&map, "root", 0.0);
-    println!("{}", me);
+    find_my_val(&map, "root", 0.0)
+}
+
+fn part2(input: &InputT) {
+    println!("{}", my_val(input));
+}
+
+/// Exact value of monkey `name` as `a * x + b`, where `x` is what `unknown`
+/// yells (if any). Panics if the result isn't linear in `x`.
+fn linear_val(
+    map: &HashMap<&str, Op>,
+    name: &str,
+    unknown: Option<&str>,
+) -> (BigRational, BigRational) {
+    let int = |n: f64| BigRational::from_integer(BigInt::from(n as i64));
+
+    if Some(name) == unknown {
+        return (int(1.0), int(0.0));
+    }
+
+    match &map[name] {
+        Op::Num(num) => (int(0.0), int(*num)),
+        Op::Expr(lhs, op, rhs) => {
+            let (a1, b1) = linear_val(map, lhs, unknown);
+            let (a2, b2) = linear_val(map, rhs, unknown);
+            match op {
+                '+' => (a1 + a2, b1 + b2),
+                '-' => (a1 - a2, b1 - b2),
+                '*' if a1.is_zero() => (&b1 * a2, b1 * b2),
+                '*' if a2.is_zero() => (a1 * &b2, b1 * b2),
+                '/' if a2.is_zero() && !b2.is_zero() => (a1 / &b2, b1 / b2),
+                c => panic!("`{name}` is not linear in `{}` (`{c}`)", unknown.unwrap_or("")),
+            }
+        }
+    }
+}
+
+/// Reference for `root_val` using exact rational arithmetic.
+fn reference_root_val(input: &InputT) -> BigRational {
+    let map = create_map(input);
+    linear_val(&map, "root", None).1
+}
+
+/// Reference for `my_val`. Solves `a * humn + b = 0` for the difference of
+/// the two sides of `root` with exact rational arithmetic.
+fn reference_my_val(input: &InputT) -> BigRational {
+    let mut map = create_map(input);
+    if let Op::Expr(lhs, _, rhs) = map["root"] {
+        map.insert("root", Op::Expr(lhs, '-', rhs));
+    }
+
+    let (a, b) = linear_val(&map, "root", Some("humn"));
+    -b / a
+}
+
+fn check() -> usize {
+    let mut mismatches = aoc::gen::compare(
+        21,
+        1,
+        0..20,
+        |input| root_val(input).to_string(),
+        |input| reference_root_val(input).to_string(),
+    );
+    mismatches += aoc::gen::compare(
+        21,
+        2,
+        0..20,
+        |input| my_val(input).to_string(),
+        |input| reference_my_val(input).to_string(),
+    );
+    mismatches
 }
 
 fn main() {
@@ -139,11 +211,19 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
+
+    if has_flag("--check") {
+        std::process::exit(check().min(1) as i32);
+    }
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    if has_flag("--reference") {
+        run_part("PART 1 (reference)", || println!("{}", reference_root_val(&input)));
+        run_part("PART 2 (reference)", || println!("{}", reference_my_val(&input)));
+        return;
+    }
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
 
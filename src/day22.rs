@@ -1,4 +1,5 @@
-use aoc::read_inputs;
+use aoc::sim::{debug, Simulation};
+use aoc::{has_flag, input_arg, read_inputs, run_part};
 use num::complex::Complex;
 use std::collections::HashMap;
 use std::fmt;
@@ -809,6 +810,137 @@ fn part2(input: &InputT) {
     println!("{sum}");
 }
 
+fn facing(direction: Complex<i32>) -> i32 {
+    match direction {
+        RIGHT => 0,
+        DOWN => 1,
+        LEFT => 2,
+        UP => 3,
+        e => panic!("Impossible direction `{e}`"),
+    }
+}
+
+/// Walks the path for the debugger, one tile or one turn per step. Without a
+/// cube the map wraps around like in part 1.
+struct WalkSim {
+    grid: Grid,
+    cube: Option<Cube>,
+    // Position on the map, or on the current face of the cube
+    pos: Complex<i32>,
+    face: usize,
+    direction: Complex<i32>,
+    path: Vec<(usize, Option<Complex<i32>>)>,
+    next: usize,
+    steps_left: usize,
+    turn: Option<Complex<i32>>,
+    trail: HashMap<Complex<i32>, Complex<i32>>,
+}
+
+impl WalkSim {
+    fn new(input: &InputT, cube: Option<Cube>) -> Self {
+        let grid = create_grid(input);
+        let pos = match cube {
+            Some(_) => Complex::new(0, 0),
+            None => {
+                let start = grid.iter().position(|x| x == &Square::Air).unwrap() - grid.ncols - 3;
+                Complex::new(start as i32, 0)
+            }
+        };
+
+        let (steps, turns) = parse_path(&input[input.len() - 1]);
+        let path = steps
+            .into_iter()
+            .enumerate()
+            .map(|(i, step)| (step, turns.get(i).copied()))
+            .collect();
+
+        let mut sim = Self {
+            grid,
+            cube,
+            pos,
+            face: 0,
+            direction: RIGHT,
+            path,
+            next: 0,
+            steps_left: 0,
+            turn: None,
+            trail: HashMap::new(),
+        };
+        sim.trail.insert(sim.map_pos(), sim.direction);
+        sim
+    }
+
+    fn map_pos(&self) -> Complex<i32> {
+        match self.cube {
+            Some(_) => rel_to_abs(self.pos, self.face),
+            None => self.pos,
+        }
+    }
+}
+
+impl Simulation for WalkSim {
+    fn step(&mut self) -> bool {
+        if self.steps_left > 0 {
+            self.steps_left -= 1;
+            match &self.cube {
+                Some(cube) => {
+                    (self.pos, self.face) =
+                        update_cube_pos(cube, self.face, self.pos, &mut self.direction, 1)
+                }
+                None => self.pos = update_pos(&self.grid, self.pos, self.direction, 1),
+            }
+        } else if let Some(turn) = self.turn.take() {
+            self.direction *= turn;
+        } else {
+            match self.path.get(self.next) {
+                Some(&(steps, turn)) => {
+                    self.steps_left = steps;
+                    self.turn = turn;
+                    self.next += 1;
+                }
+                None => return false,
+            }
+            return self.step();
+        }
+        self.trail.insert(self.map_pos(), self.direction);
+        true
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for y in 0..self.grid.nrows {
+            for x in 0..self.grid.ncols {
+                let pos = Complex::new(x as i32, y as i32);
+                out.push(match (self.trail.get(&pos), &self.grid[(x, y)]) {
+                    _ if pos == self.map_pos() => '@',
+                    (Some(&RIGHT), _) => '>',
+                    (Some(&DOWN), _) => 'v',
+                    (Some(&LEFT), _) => '<',
+                    (Some(_), _) => '^',
+                    (None, Square::Air) => '.',
+                    (None, Square::Rock) => '#',
+                    (None, Square::Edge) => ' ',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn state(&self) -> Vec<(&'static str, i64)> {
+        let pos = self.map_pos();
+        let password = 1000 * (pos.im + 1) + 4 * (pos.re + 1) + facing(self.direction);
+        vec![
+            ("instruction", self.next as i64),
+            ("row", (pos.im + 1) as i64),
+            ("column", (pos.re + 1) as i64),
+            ("facing", facing(self.direction) as i64),
+            ("face", self.face as i64),
+            ("password", password as i64),
+        ]
+    }
+}
+
 fn main() {
     let day: u32 = 22;
     #[cfg(debug_assertions)]
@@ -816,10 +948,14 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("\nPART 2:");
-    part2(&input);
+    if has_flag("--debug") {
+        let cube = has_flag("--part2").then(|| create_cube(&input));
+        debug(WalkSim::new(&input, cube));
+        return;
+    }
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
@@ -1,4 +1,5 @@
-use aoc::read_inputs;
+use aoc::sim::{debug, Simulation};
+use aoc::{has_flag, input_arg, read_inputs, run_part};
 use std::collections::HashMap;
 
 type InputT = Vec<String>;
@@ -20,20 +21,26 @@ enum Direction {
     East,
 }
 
-#[allow(dead_code)]
-fn print_grid(grid: &Grid, bb: (i32, i32, i32, i32)) {
+fn draw_grid(grid: &Grid, bb: (i32, i32, i32, i32)) -> String {
     let (x1, y1, x2, y2) = bb;
 
+    let mut out = String::new();
     for y in y1..=y2 {
         for x in x1..=x2 {
             if grid.contains_key(&(x, y)) {
-                print!("#");
+                out.push('#');
             } else {
-                print!(".");
+                out.push('.');
             }
         }
-        println!();
+        out.push('\n');
     }
+    out
+}
+
+#[allow(dead_code)]
+fn print_grid(grid: &Grid, bb: (i32, i32, i32, i32)) {
+    print!("{}", draw_grid(grid, bb));
 }
 
 fn parse_grid(input: &InputT) -> Grid {
@@ -231,6 +238,56 @@ fn part2(input: &InputT) {
     println!("{round}");
 }
 
+/// Runs the rounds one at a time for the debugger, until `end` rounds or
+/// until no elf moves
+struct ElfSim {
+    grid: Grid,
+    end: usize,
+    round: usize,
+    moved: usize,
+}
+
+impl ElfSim {
+    fn new(input: &InputT, end: usize) -> Self {
+        Self {
+            grid: parse_grid(input),
+            end,
+            round: 0,
+            moved: 0,
+        }
+    }
+}
+
+impl Simulation for ElfSim {
+    fn step(&mut self) -> bool {
+        if self.round == self.end || (self.round > 0 && self.moved == 0) {
+            return false;
+        }
+
+        let old = self.grid.clone();
+        first_half(&mut self.grid, self.round);
+        second_half(&mut self.grid);
+        self.moved = self.grid.keys().filter(|k| !old.contains_key(k)).count();
+        self.round += 1;
+        true
+    }
+
+    fn render(&self) -> String {
+        draw_grid(&self.grid, bounding_box(&self.grid))
+    }
+
+    fn state(&self) -> Vec<(&'static str, i64)> {
+        let (x1, y1, x2, y2) = bounding_box(&self.grid);
+        let area = (x2 - x1 + 1) * (y2 - y1 + 1);
+        vec![
+            ("round", self.round as i64),
+            ("elves", self.grid.len() as i64),
+            ("moved", self.moved as i64),
+            ("empty", area as i64 - self.grid.len() as i64),
+        ]
+    }
+}
+
 fn main() {
     let day: u32 = 23;
     #[cfg(debug_assertions)]
@@ -238,10 +295,14 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    if has_flag("--debug") {
+        let end = if has_flag("--part2") { usize::MAX } else { 10 };
+        debug(ElfSim::new(&input, end));
+        return;
+    }
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }
@@ -1,4 +1,5 @@
-use aoc::read_inputs;
+use aoc::math::lcm;
+use aoc::{input_arg, read_inputs, run_part};
 use num::complex::Complex;
 use std::collections::{HashSet, HashMap, VecDeque};
 use std::fmt;
@@ -175,7 +176,8 @@ fn update_bliz(grid: &Grid) -> Grid {
 }
 
 fn find_all_grids(grid: Grid) -> Vec<Grid> {
-    let repeat = (grid.nrows - 2) * (grid.ncols - 2);
+    // The blizzards are back where they started after this many minutes
+    let repeat = lcm([grid.nrows - 2, grid.ncols - 2]);
     let mut ret = vec![grid];
     ret.reserve(repeat - 1);
 
@@ -198,9 +200,11 @@ fn dijkstra(grids: &Vec<Grid>, start: Complex<i32>, end: Complex<i32>, steps: us
         .filter(|x| grids[(steps + 1) % grids.len()][x] == 0)
         .collect();
     
+    // The blizzards repeat, so only the time modulo their period matters for
+    // the positions already seen. Without a path the queue then runs out.
     for n in neighbors {
         queue.push_back((n, steps + 1));
-        seen.insert((n, steps + 1));
+        seen.insert((n, (steps + 1) % grids.len()));
     }
     
     while let Some((pos, steps)) = queue.pop_front() {
@@ -215,7 +219,7 @@ fn dijkstra(grids: &Vec<Grid>, start: Complex<i32>, end: Complex<i32>, steps: us
             .collect();
         
         for n in neighbors {
-            if seen.insert((n, steps + 1)) {
+            if seen.insert((n, (steps + 1) % grids.len())) {
                 queue.push_back((n, steps + 1));
             }
         }
@@ -252,10 +256,28 @@ fn main() {
 
     #[cfg(not(debug_assertions))]
     let file_path = format!("data/{:02}.txt", day);
+    let file_path = input_arg().unwrap_or(file_path);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    part2(&input);
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generated_valleys() {
+        for seed in 0..10 {
+            let input = aoc::gen::generate(24, seed, aoc::gen::default_size(24)).unwrap();
+            let grids = find_all_grids(parse_grid(&input));
+            let (start, end) = (Complex::new(1, 0), grids[0].end_pos);
+
+            let there = dijkstra(&grids, start, end, 0);
+            let back = dijkstra(&grids, end, start, there);
+            let again = dijkstra(&grids, start, end, back);
+            assert!(0 < there && there < back && back < again, "seed {seed}");
+        }
+    }
 }
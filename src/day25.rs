@@ -1,4 +1,4 @@
-use aoc::read_inputs;
+use aoc::{read_inputs, run_part};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 
@@ -121,8 +121,7 @@ fn main() {
     let file_path = format!("data/{:02}.txt", day);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("FINAL RESULT:");
-    part1(&input);
+    run_part("FINAL RESULT", || part1(&input));
 }
 
 #[cfg(test)]
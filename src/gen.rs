@@ -0,0 +1,724 @@
+//! Random puzzle-input generators used to stress test the solutions.
+//!
+//! Every generator is deterministic for a given seed, so an input that breaks
+//! a solution can be reproduced with `aoc gen <day> --seed <seed>`.
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::fmt::Debug;
+use std::ops::Range;
+use std::panic::{self, RefUnwindSafe};
+
+/// Days that have an input generator.
+pub const DAYS: [u32; 8] = [11, 15, 16, 17, 19, 21, 23, 24];
+
+/// Small seeded PRNG (splitmix64). Good enough for generating inputs and
+/// avoids pulling in an extra dependency.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in `[lo, hi)`.
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        assert!(lo < hi, "Empty range {lo}..{hi}");
+        lo + (self.next_u64() % (hi - lo) as u64) as i64
+    }
+
+    /// `true` with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 <= p
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.range(0, items.len() as i64) as usize]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.range(0, i as i64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// Size used by `aoc gen` when none is given. Roughly the size of the
+/// example inputs, so the debug builds of the solutions stay fast.
+pub fn default_size(day: u32) -> usize {
+    match day {
+        11 => 4,
+        15 => 14,
+        16 => 10,
+        17 => 40,
+        19 => 3,
+        21 => 5,
+        23 => 12,
+        24 => 6,
+        _ => 10,
+    }
+}
+
+/// Generates an input for `day`, or `None` if there is no generator for it.
+pub fn generate(day: u32, seed: u64, size: usize) -> Option<Vec<String>> {
+    let mut rng = Rng::new(seed);
+    let input = match day {
+        11 => monkeys(&mut rng, size),
+        15 => sensors(&mut rng, size),
+        16 => valves(&mut rng, size),
+        17 => jets(&mut rng, size),
+        19 => blueprints(&mut rng, size),
+        21 => monkey_tree(&mut rng, size),
+        23 => elf_field(&mut rng, size),
+        24 => blizzard_valley(&mut rng, size),
+        _ => return None,
+    };
+    Some(input)
+}
+
+/// Day 11: `size` monkeys (2 to 10). Every monkey tests divisibility by a
+/// different prime and throws to two other monkeys. Worry levels stay within
+/// 64 bits for the 20 rounds of part 1, like in the puzzle.
+pub fn monkeys(rng: &mut Rng, size: usize) -> Vec<String> {
+    loop {
+        let monkeys = random_monkeys(rng, size);
+        if !calm_monkeys(&monkeys) {
+            continue;
+        }
+
+        let mut lines = vec![];
+        for (i, monkey) in monkeys.iter().enumerate() {
+            let items: Vec<String> = monkey.items.iter().map(u64::to_string).collect();
+            let op = match monkey.op {
+                MonkeyOp::Square => String::from("old * old"),
+                MonkeyOp::Mul(n) => format!("old * {n}"),
+                MonkeyOp::Add(n) => format!("old + {n}"),
+            };
+            lines.push(format!("Monkey {i}:"));
+            lines.push(format!("  Starting items: {}", items.join(", ")));
+            lines.push(format!("  Operation: new = {op}"));
+            lines.push(format!("  Test: divisible by {}", monkey.prime));
+            lines.push(format!("    If true: throw to monkey {}", monkey.if_true));
+            lines.push(format!("    If false: throw to monkey {}", monkey.if_false));
+            lines.push(String::new());
+        }
+        lines.pop();
+        return lines;
+    }
+}
+
+enum MonkeyOp {
+    Square,
+    Mul(u64),
+    Add(u64),
+}
+
+struct Monkey {
+    items: Vec<u64>,
+    op: MonkeyOp,
+    prime: u64,
+    if_true: usize,
+    if_false: usize,
+}
+
+fn random_monkeys(rng: &mut Rng, size: usize) -> Vec<Monkey> {
+    const PRIMES: [u64; 10] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29];
+    let size = size.clamp(2, PRIMES.len());
+
+    let mut primes = PRIMES.to_vec();
+    rng.shuffle(&mut primes);
+
+    let mut monkeys = vec![];
+    for (i, &prime) in primes.iter().enumerate().take(size) {
+        let items = (0..rng.range(1, 5))
+            .map(|_| rng.range(50, 100) as u64)
+            .collect();
+        let op = match rng.range(0, 6) {
+            0 => MonkeyOp::Square,
+            1 | 2 => MonkeyOp::Mul(rng.range(2, 20) as u64),
+            _ => MonkeyOp::Add(rng.range(1, 9) as u64),
+        };
+
+        let mut others: Vec<usize> = (0..size).filter(|&j| j != i).collect();
+        rng.shuffle(&mut others);
+        let if_false = *others.get(1).unwrap_or(&others[0]);
+        monkeys.push(Monkey {
+            items,
+            op,
+            prime,
+            if_true: others[0],
+            if_false,
+        });
+    }
+    monkeys
+}
+
+/// Plays the 20 rounds of part 1, false if a worry level overflows 64 bits
+fn calm_monkeys(monkeys: &[Monkey]) -> bool {
+    let mut items: Vec<Vec<u64>> = monkeys.iter().map(|m| m.items.clone()).collect();
+    for _ in 0..20 {
+        for (i, monkey) in monkeys.iter().enumerate() {
+            for item in std::mem::take(&mut items[i]) {
+                let level = match monkey.op {
+                    MonkeyOp::Square => item.checked_mul(item),
+                    MonkeyOp::Mul(n) => item.checked_mul(n),
+                    MonkeyOp::Add(n) => item.checked_add(n),
+                };
+                let Some(level) = level.map(|level| level / 3) else {
+                    return false;
+                };
+                let target = if level.is_multiple_of(monkey.prime) {
+                    monkey.if_true
+                } else {
+                    monkey.if_false
+                };
+                items[target].push(level);
+            }
+        }
+    }
+    true
+}
+
+/// Day 15: at least `size` sensors. Every sensor reports a beacon that is
+/// strictly the closest one to it, as the puzzle guarantees, and exactly one
+/// position of the example's search square (0 to 20) is out of range of every
+/// sensor: the distress beacon of part 2.
+pub fn sensors(rng: &mut Rng, size: usize) -> Vec<String> {
+    loop {
+        if let Some(lines) = try_sensors(rng, size) {
+            return lines;
+        }
+    }
+}
+
+/// Sensor position, with its beacon and range
+type Sensor = ((i64, i64), (i64, i64), i64);
+
+/// Covers the search square around a random gap, `None` if it gets stuck
+fn try_sensors(rng: &mut Rng, size: usize) -> Option<Vec<String>> {
+    const MAX_COORD: i64 = 20;
+    let dist = |a: (i64, i64), b: (i64, i64)| (a.0 - b.0).abs() + (a.1 - b.1).abs();
+    let gap = (rng.range(0, MAX_COORD + 1), rng.range(0, MAX_COORD + 1));
+
+    let mut sensors: Vec<Sensor> = vec![];
+    let uncovered = |sensors: &[Sensor]| -> Vec<(i64, i64)> {
+        (0..=MAX_COORD)
+            .flat_map(|y| (0..=MAX_COORD).map(move |x| (x, y)))
+            .filter(|&p| sensors.iter().all(|&(s, _, r)| dist(s, p) > r))
+            .collect()
+    };
+    // Adds a sensor at `s` with a range in `min..=max`, as large as possible
+    let add = |rng: &mut Rng, sensors: &mut Vec<_>, s: (i64, i64), min: i64, max: i64| {
+        if sensors.iter().any(|&(other, b, _)| other == s || b == s) {
+            return;
+        }
+        // Any other beacon must be strictly further away than the sensor's
+        let max = sensors
+            .iter()
+            .map(|&(_, b, _)| dist(s, b) - 1)
+            .fold(max, i64::min);
+        for r in (min.max(1)..=max).rev() {
+            let ring: Vec<(i64, i64)> = (0..4 * r)
+                .map(|i| {
+                    let (side, k) = (i / r, i % r);
+                    match side {
+                        0 => (s.0 + r - k, s.1 + k),
+                        1 => (s.0 - k, s.1 + r - k),
+                        2 => (s.0 - r + k, s.1 - k),
+                        _ => (s.0 + k, s.1 - r + k),
+                    }
+                })
+                // The beacon can't be closer to another sensor than its own
+                .filter(|&b| sensors.iter().all(|&(other, _, r)| dist(other, b) > r))
+                .collect();
+            if !ring.is_empty() {
+                let b = *rng.choose(&ring);
+                sensors.push((s, b, r));
+                return;
+            }
+        }
+    };
+
+    let mut attempts = 0;
+    loop {
+        let left: Vec<(i64, i64)> = uncovered(&sensors)
+            .into_iter()
+            .filter(|&p| p != gap)
+            .collect();
+        if left.is_empty() {
+            break;
+        }
+        attempts += 1;
+        if attempts > 100 * (MAX_COORD * MAX_COORD) as usize {
+            return None;
+        }
+
+        // A sensor near a position left, reaching it but not the gap
+        let &q = rng.choose(&left);
+        let s = (q.0 + rng.range(-4, 5), q.1 + rng.range(-4, 5));
+        if dist(s, q) < dist(s, gap) {
+            add(rng, &mut sensors, s, dist(s, q), dist(s, gap) - 1);
+        }
+    }
+    while sensors.len() < size && attempts < 1000 * size {
+        attempts += 1;
+        let s = (rng.range(-5, MAX_COORD + 6), rng.range(-5, MAX_COORD + 6));
+        add(rng, &mut sensors, s, 1, dist(s, gap) - 1);
+    }
+
+    if uncovered(&sensors) != [gap] {
+        return None;
+    }
+    rng.shuffle(&mut sensors);
+    let lines = sensors
+        .iter()
+        .map(|&(s, b, _)| {
+            format!(
+                "Sensor at x={}, y={}: closest beacon is at x={}, y={}",
+                s.0, s.1, b.0, b.1
+            )
+        })
+        .collect();
+    Some(lines)
+}
+
+/// Day 16: a connected tunnel system of `size` valves (at least 2) starting at
+/// `AA`. At most 15 valves have a non-zero flow rate.
+pub fn valves(rng: &mut Rng, size: usize) -> Vec<String> {
+    let size = size.clamp(2, 26 * 26);
+
+    let mut names: Vec<String> = vec![];
+    for a in b'A'..=b'Z' {
+        for b in b'A'..=b'Z' {
+            if (a, b) != (b'A', b'A') {
+                names.push(String::from_utf8(vec![a, b]).unwrap());
+            }
+        }
+    }
+    rng.shuffle(&mut names);
+    names.truncate(size - 1);
+    names.insert(0, String::from("AA"));
+
+    // Random spanning tree, then a few extra tunnels
+    let mut edges: BTreeSet<(usize, usize)> = BTreeSet::new();
+    for i in 1..size {
+        let j = rng.range(0, i as i64) as usize;
+        edges.insert((j, i));
+    }
+    for _ in 0..size / 2 {
+        let i = rng.range(0, size as i64) as usize;
+        let j = rng.range(0, size as i64) as usize;
+        if i != j {
+            edges.insert((i.min(j), i.max(j)));
+        }
+    }
+
+    let mut flow_rates = vec![0; size];
+    let mut working: Vec<usize> = (1..size).collect();
+    rng.shuffle(&mut working);
+    for &i in working.iter().take((size / 3).clamp(1, 15)) {
+        flow_rates[i] = rng.range(1, 26);
+    }
+
+    let mut lines = vec![];
+    for (i, name) in names.iter().enumerate() {
+        let mut neighbors: Vec<&str> = edges
+            .iter()
+            .filter_map(|&(a, b)| match (a == i, b == i) {
+                (true, _) => Some(&names[b][..]),
+                (_, true) => Some(&names[a][..]),
+                _ => None,
+            })
+            .collect();
+        rng.shuffle(&mut neighbors);
+
+        let tunnels = if neighbors.len() == 1 {
+            format!("tunnel leads to valve {}", neighbors[0])
+        } else {
+            format!("tunnels lead to valves {}", neighbors.join(", "))
+        };
+        lines.push(format!(
+            "Valve {} has flow rate={}; {}",
+            name, flow_rates[i], tunnels
+        ));
+    }
+    rng.shuffle(&mut lines);
+    lines
+}
+
+/// Day 17: a jet pattern of `size` pushes.
+pub fn jets(rng: &mut Rng, size: usize) -> Vec<String> {
+    let pattern = (0..size.max(1))
+        .map(|_| if rng.chance(0.5) { '<' } else { '>' })
+        .collect();
+    vec![pattern]
+}
+
+/// Day 19: `size` blueprints (at least 3, as part 2 needs three of them).
+pub fn blueprints(rng: &mut Rng, size: usize) -> Vec<String> {
+    (1..=size.max(3))
+        .map(|i| {
+            format!(
+                "Blueprint {}: \
+                Each ore robot costs {} ore. \
+                Each clay robot costs {} ore. \
+                Each obsidian robot costs {} ore and {} clay. \
+                Each geode robot costs {} ore and {} obsidian.",
+                i,
+                rng.range(2, 5),
+                rng.range(2, 5),
+                rng.range(2, 5),
+                rng.range(5, 21),
+                rng.range(2, 5),
+                rng.range(5, 21),
+            )
+        })
+        .collect()
+}
+
+struct MonkeyTree<'a> {
+    rng: &'a mut Rng,
+    names: HashSet<String>,
+    lines: Vec<String>,
+}
+
+impl<'a> MonkeyTree<'a> {
+    fn name(&mut self) -> String {
+        loop {
+            let name: String = (0..4)
+                .map(|_| (b'a' + self.rng.range(0, 26) as u8) as char)
+                .collect();
+            if name != "root" && name != "humn" && self.names.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+
+    fn push_op(&mut self, name: &str, lhs: &str, op: char, rhs: &str) {
+        self.lines.push(format!("{name}: {lhs} {op} {rhs}"));
+    }
+
+    /// Adds a subtree (without `humn`) whose root yells `value` and returns
+    /// the name of its root. Every division is exact.
+    fn constant(&mut self, value: i64, depth: usize) -> String {
+        let name = self.name();
+
+        if depth == 0 || value <= 1 || self.rng.chance(0.25) {
+            self.lines.push(format!("{name}: {value}"));
+            return name;
+        }
+
+        let divisor = (2..10).find(|d| value % d == 0 && value > *d);
+        let (lhs, op, rhs) = match (self.rng.range(0, 4), divisor) {
+            (0, _) | (2, None) => {
+                let a = self.rng.range(1, value);
+                (a, '+', value - a)
+            }
+            (1, _) => {
+                let b = self.rng.range(1, 10);
+                (value + b, '-', b)
+            }
+            (2, Some(d)) => (value / d, '*', d),
+            _ => {
+                let d = self.rng.range(2, 6);
+                (value * d, '/', d)
+            }
+        };
+        let lhs = self.constant(lhs, depth - 1);
+        let rhs = self.constant(rhs, depth - 1);
+        self.push_op(&name, &lhs, op, &rhs);
+        name
+    }
+}
+
+/// Day 21: a monkey riddle where `humn` is `size` operations away from
+/// `root`. The monkeys on the `humn` path yell `p * humn + q`, which is kept
+/// positive and exactly divisible both for the listed `humn` value and for
+/// the (integer) answer of part 2.
+pub fn monkey_tree(rng: &mut Rng, size: usize) -> Vec<String> {
+    const MAX_VALUE: i64 = 1 << 40;
+
+    let humn = rng.range(1, 1000);
+    let answer = rng.range(1, 100_000);
+    let mut tree = MonkeyTree {
+        rng,
+        names: HashSet::new(),
+        lines: vec![format!("humn: {humn}")],
+    };
+
+    let (mut p, mut q) = (1i64, 0i64);
+    let mut head = String::from("humn");
+    for _ in 0..size {
+        let values = [p * humn + q, p * answer + q];
+        let (vmin, vmax) = (values[0].min(values[1]), values[0].max(values[1]));
+        let gcd = num::integer::gcd(p, q);
+        let divisor = (2..10).find(|d| gcd % d == 0);
+
+        let name = tree.name();
+        match tree.rng.range(0, 5) {
+            0 if vmin > 2 => {
+                let c = tree.rng.range(1, vmin.min(20));
+                let other = tree.constant(c, 2);
+                tree.push_op(&name, &head, '-', &other);
+                q -= c;
+            }
+            1 => {
+                let c = vmax + tree.rng.range(1, 20);
+                let other = tree.constant(c, 2);
+                tree.push_op(&name, &other, '-', &head);
+                (p, q) = (-p, c - q);
+            }
+            2 if vmax < MAX_VALUE => {
+                let c = tree.rng.range(2, 4);
+                let other = tree.constant(c, 1);
+                if tree.rng.chance(0.5) {
+                    tree.push_op(&name, &head, '*', &other);
+                } else {
+                    tree.push_op(&name, &other, '*', &head);
+                }
+                (p, q) = (p * c, q * c);
+            }
+            3 if divisor.is_some() => {
+                let c = divisor.unwrap();
+                let other = tree.constant(c, 1);
+                tree.push_op(&name, &head, '/', &other);
+                (p, q) = (p / c, q / c);
+            }
+            _ => {
+                let c = tree.rng.range(1, 20);
+                let other = tree.constant(c, 2);
+                if tree.rng.chance(0.5) {
+                    tree.push_op(&name, &head, '+', &other);
+                } else {
+                    tree.push_op(&name, &other, '+', &head);
+                }
+                q += c;
+            }
+        }
+        head = name;
+    }
+
+    let other = tree.constant(p * answer + q, 3);
+    if tree.rng.chance(0.5) {
+        tree.push_op("root", &head, '+', &other);
+    } else {
+        tree.push_op("root", &other, '+', &head);
+    }
+
+    let mut lines = tree.lines;
+    rng.shuffle(&mut lines);
+    lines
+}
+
+/// Day 23: a `size`×`size` field of elves.
+pub fn elf_field(rng: &mut Rng, size: usize) -> Vec<String> {
+    (0..size)
+        .map(|_| {
+            (0..size)
+                .map(|_| if rng.chance(0.4) { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+/// Day 24: a valley with a `size` wide interior. No vertical blizzards are
+/// placed in the columns of the entrance and the exit, like in the puzzle, and
+/// the valley can be crossed there and back again.
+pub fn blizzard_valley(rng: &mut Rng, size: usize) -> Vec<String> {
+    let width = size.max(2);
+    let height = (size / 3).max(2);
+
+    loop {
+        let mut lines = vec![format!("#.{}", "#".repeat(width))];
+        for _ in 0..height {
+            let mut ln = String::from("#");
+            for x in 0..width {
+                let vertical_allowed = x != 0 && x != width - 1;
+                let c = match rng.range(0, 8) {
+                    0 => '>',
+                    1 => '<',
+                    2 if vertical_allowed => '^',
+                    3 if vertical_allowed => 'v',
+                    _ => '.',
+                };
+                ln.push(c);
+            }
+            ln.push('#');
+            lines.push(ln);
+        }
+        lines.push(format!("{}.#", "#".repeat(width)));
+
+        let (start, end) = ((1, 0), (width, height + 1));
+        let there = valley_crossing(&lines, start, end, 0);
+        let back = there.and_then(|t| valley_crossing(&lines, end, start, t));
+        if back
+            .and_then(|t| valley_crossing(&lines, start, end, t))
+            .is_some()
+        {
+            return lines;
+        }
+    }
+}
+
+/// Earliest minute the expedition can get from `from` to `to` in the valley
+/// drawn by `lines`, leaving at minute `t`. `None` if it never can.
+fn valley_crossing(
+    lines: &[String],
+    from: (usize, usize),
+    to: (usize, usize),
+    t: usize,
+) -> Option<usize> {
+    let grid: Vec<&[u8]> = lines.iter().map(|ln| ln.as_bytes()).collect();
+    let (w, h) = (grid[0].len() - 2, grid.len() - 2);
+    // The blizzards are back where they started after this many minutes
+    let period = crate::math::lcm([w, h]);
+
+    let is_free = |(x, y): (usize, usize), t: usize| {
+        if y == 0 || y == h + 1 {
+            return grid[y][x] == b'.';
+        }
+        if x == 0 || x == w + 1 {
+            return false;
+        }
+        // Blizzards that would be on the position at minute `t`
+        let (x, y) = (x - 1, y - 1);
+        grid[y + 1][(x + w - t % w) % w + 1] != b'>'
+            && grid[y + 1][(x + t) % w + 1] != b'<'
+            && grid[(y + h - t % h) % h + 1][x + 1] != b'v'
+            && grid[(y + t) % h + 1][x + 1] != b'^'
+    };
+
+    // Waiting longer than a period in the same position never helps
+    let mut seen = HashSet::from([(from, t % period)]);
+    let mut queue = VecDeque::from([(from, t)]);
+    while let Some(((x, y), t)) = queue.pop_front() {
+        if (x, y) == to {
+            return Some(t);
+        }
+        let moves = [
+            (x, y),
+            (x + 1, y),
+            (x.wrapping_sub(1), y),
+            (x, y + 1),
+            (x, y.wrapping_sub(1)),
+        ];
+        for pos in moves {
+            if pos.0 > w + 1 || pos.1 > h + 1 || !is_free(pos, t + 1) {
+                continue;
+            }
+            if seen.insert((pos, (t + 1) % period)) {
+                queue.push_back((pos, t + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Runs `fast` and `reference` on the generated inputs of `day` for every seed
+/// in `seeds` and prints the seeds where they disagree or panic. Returns the
+/// number of disagreements.
+pub fn compare<T, F, R>(day: u32, part: u32, seeds: Range<u64>, fast: F, reference: R) -> usize
+where
+    T: PartialEq + Debug,
+    F: Fn(&Vec<String>) -> T + RefUnwindSafe,
+    R: Fn(&Vec<String>) -> T + RefUnwindSafe,
+{
+    let run = |f: &dyn Fn() -> T| {
+        panic::catch_unwind(panic::AssertUnwindSafe(f)).map_err(|e| {
+            match (e.downcast_ref::<&str>(), e.downcast_ref::<String>()) {
+                (Some(msg), _) => msg.to_string(),
+                (_, Some(msg)) => msg.clone(),
+                _ => String::from("unknown panic"),
+            }
+        })
+    };
+
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut mismatches = 0;
+    for seed in seeds {
+        let input = generate(day, seed, default_size(day)).unwrap();
+        let fast = run(&|| fast(&input));
+        let reference = run(&|| reference(&input));
+        if fast != reference {
+            mismatches += 1;
+            println!("seed {seed}, part {part}: fast = {fast:?}, reference = {reference:?}");
+        }
+    }
+
+    panic::set_hook(hook);
+    mismatches
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_input() {
+        for day in DAYS {
+            let size = default_size(day);
+            assert_eq!(generate(day, 42, size), generate(day, 42, size));
+            assert_ne!(generate(day, 1, size), generate(day, 2, size));
+        }
+    }
+
+    #[test]
+    fn sensors_leave_one_gap() {
+        for seed in 0..10 {
+            let lines = generate(15, seed, default_size(15)).unwrap();
+            assert!(lines.len() >= default_size(15));
+            let sensors: Vec<((i64, i64), i64)> = lines
+                .iter()
+                .map(|ln| {
+                    let nums: Vec<i64> = ln
+                        .split(|c: char| c != '-' && !c.is_ascii_digit())
+                        .filter_map(|n| n.parse().ok())
+                        .collect();
+                    let r = (nums[0] - nums[2]).abs() + (nums[1] - nums[3]).abs();
+                    ((nums[0], nums[1]), r)
+                })
+                .collect();
+
+            let gaps = (0..=20)
+                .flat_map(|y| (0..=20).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    sensors
+                        .iter()
+                        .all(|&((sx, sy), r)| (sx - x).abs() + (sy - y).abs() > r)
+                })
+                .count();
+            assert_eq!(gaps, 1, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn monkey_tree_is_consistent() {
+        let mut rng = Rng::new(7);
+        let lines = monkey_tree(&mut rng, 20);
+
+        let names: HashSet<&str> = lines.iter().map(|ln| &ln[..4]).collect();
+        assert_eq!(names.len(), lines.len());
+        assert!(names.contains("root") && names.contains("humn"));
+        for ln in lines.iter() {
+            for token in ln[6..].split(' ').filter(|t| t.len() == 4) {
+                assert!(
+                    token.parse::<i64>().is_ok() || names.contains(token),
+                    "Unknown monkey `{token}`"
+                );
+            }
+        }
+    }
+}
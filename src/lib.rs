@@ -1,5 +1,15 @@
 use std::str::FromStr;
 
+#[cfg(feature = "alloc-stats")]
+pub mod alloc;
+pub mod gen;
+pub mod math;
+pub mod ocr;
+pub mod runner;
+pub mod sim;
+
+pub use runner::run_part;
+
 pub fn read_inputs<T>(file_path: &str) -> std::io::Result<Vec<T>>
 where
     T: FromStr,
@@ -10,6 +20,25 @@ where
         .collect())
 }
 
+/// Returns the first positional (not `--flag`) command line argument. The
+/// solutions use it to read an input file other than the default one.
+pub fn input_arg() -> Option<String> {
+    std::env::args().skip(1).find(|arg| !arg.starts_with("--"))
+}
+
+/// Whether `flag` (e.g. `--reference`) was passed on the command line.
+pub fn has_flag(flag: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == flag)
+}
+
+/// Value of a `--flag=value` command line argument.
+pub fn flag_value(flag: &str) -> Option<String> {
+    std::env::args().skip(1).find_map(|arg| {
+        let (name, value) = arg.split_once('=')?;
+        (name == flag).then(|| value.to_string())
+    })
+}
+
 #[cfg(test)]
 mod test {
     use crate::read_inputs;
@@ -1,3 +1,103 @@
+use aoc::{gen, sim};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn usage() {
+    eprintln!("Usage: aoc <command> [args]");
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("    gen <day> [--seed N] [--size N]    Print a random puzzle input for <day>");
+    eprintln!("    debug <day> [input] [--part2]      Step through the simulation of <day>");
+}
+
+fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Result<Option<T>, String> {
+    match args.iter().position(|arg| arg == flag) {
+        None => Ok(None),
+        Some(i) => match args.get(i + 1).map(|val| val.parse::<T>()) {
+            Some(Ok(val)) => Ok(Some(val)),
+            _ => Err(format!("`{flag}` expects a positive integer")),
+        },
+    }
+}
+
+fn gen_cmd(args: &[String]) -> Result<(), String> {
+    let day: u32 = match args.first().map(|day| day.parse()) {
+        Some(Ok(day)) => day,
+        _ => return Err(String::from("Missing or invalid day number")),
+    };
+    if !gen::DAYS.contains(&day) {
+        return Err(format!(
+            "No generator for day {day}. Available days: {:?}",
+            gen::DAYS
+        ));
+    }
+
+    let seed = match parse_flag(args, "--seed")? {
+        Some(seed) => seed,
+        None => {
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|t| t.as_nanos() as u64)
+                .unwrap_or(0);
+            eprintln!("seed: {seed}");
+            seed
+        }
+    };
+    let size = parse_flag(args, "--size")?.unwrap_or_else(|| gen::default_size(day));
+
+    for line in gen::generate(day, seed, size).unwrap() {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn debug_cmd(args: &[String]) -> Result<(), String> {
+    let day: u32 = match args.first().map(|day| day.parse()) {
+        Some(Ok(day)) => day,
+        _ => return Err(String::from("Missing or invalid day number")),
+    };
+    if !sim::DAYS.contains(&day) {
+        return Err(format!(
+            "No debugger for day {day}. Available days: {:?}",
+            sim::DAYS
+        ));
+    }
+
+    // The simulations live in the binaries of each day, built next to this one
+    let name = format!("day{day:02}");
+    let exe = std::env::current_exe()
+        .map_err(|e| e.to_string())?
+        .with_file_name(format!("{name}{}", std::env::consts::EXE_SUFFIX));
+    if !exe.exists() {
+        return Err(format!(
+            "`{}` not found, build it first with `cargo build --bin {name}`",
+            exe.display()
+        ));
+    }
+
+    let status = Command::new(exe)
+        .args(&args[1..])
+        .arg("--debug")
+        .status()
+        .map_err(|e| e.to_string())?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(|cmd| &cmd[..]) {
+        Some("gen") => gen_cmd(&args[1..]),
+        Some("debug") => debug_cmd(&args[1..]),
+        _ => {
+            usage();
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("{e}");
+        usage();
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,148 @@
+//! Integer helpers shared by the solutions: gcd and lcm of many numbers,
+//! modular arithmetic and the Chinese remainder theorem. The `checked_` and
+//! `big_` variants are for when the result may not fit in a machine integer.
+
+use num::integer::Integer;
+use num::{BigInt, BigUint, CheckedMul, Signed};
+
+/// Greatest common divisor of all `values`, 0 if there are none.
+pub fn gcd<T: Integer + Clone>(values: impl IntoIterator<Item = T>) -> T {
+    values.into_iter().fold(T::zero(), |acc, x| acc.gcd(&x))
+}
+
+/// Least common multiple of all `values`, 1 if there are none.
+pub fn lcm<T: Integer + Clone>(values: impl IntoIterator<Item = T>) -> T {
+    values.into_iter().fold(T::one(), |acc, x| acc.lcm(&x))
+}
+
+/// Like [`lcm`], but `None` if the result overflows `T`.
+pub fn checked_lcm<T: Integer + CheckedMul + Clone>(
+    values: impl IntoIterator<Item = T>,
+) -> Option<T> {
+    values.into_iter().try_fold(T::one(), |acc, x| {
+        if x.is_zero() {
+            return Some(T::zero());
+        }
+        (acc.clone() / acc.gcd(&x)).checked_mul(&x)
+    })
+}
+
+/// Like [`lcm`], but never overflows.
+pub fn big_lcm<T: Into<BigUint>>(values: impl IntoIterator<Item = T>) -> BigUint {
+    lcm(values.into_iter().map(Into::into))
+}
+
+/// Euclidean modulo: the remainder of `a / m` in `0..|m|`, also for negative
+/// `a` or `m`. Handy to wrap indices around a circular list.
+pub fn modulo<T: Integer + Clone>(a: T, m: T) -> T {
+    let r = a.mod_floor(&m);
+    if r < T::zero() {
+        r - m
+    } else {
+        r
+    }
+}
+
+/// `a * b % m` without overflowing.
+pub fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+/// The `x` in `0..m` such that `a * x` is 1 modulo `m`, if there is one
+/// (i.e. if `a` and `m` are coprime).
+pub fn mod_inverse<T: Integer + Clone + Signed>(a: T, m: T) -> Option<T> {
+    let egcd = modulo(a, m.clone()).extended_gcd(&m);
+    if egcd.gcd.is_one() {
+        Some(modulo(egcd.x, m))
+    } else {
+        None
+    }
+}
+
+/// Combines the congruences `x = r (mod m)` into a single `(r, m)` with `m`
+/// the lcm of all moduli. The moduli need not be coprime. `None` if the
+/// congruences contradict each other.
+fn solve_crt<T: Integer + Clone + Signed>(
+    congruences: impl IntoIterator<Item = (T, T)>,
+    fits: impl Fn(&T) -> bool,
+) -> Option<(T, T)> {
+    let mut ret = (T::zero(), T::one());
+    for (r2, m2) in congruences {
+        let (r1, m1) = ret;
+        let egcd = m1.extended_gcd(&m2);
+        let (quot, rem) = (r2 - r1.clone()).div_rem(&egcd.gcd);
+        if !rem.is_zero() {
+            return None;
+        }
+
+        let step = m2 / egcd.gcd.clone();
+        let modulus = m1.clone() * step.clone();
+        if !fits(&modulus) {
+            return None;
+        }
+        let k = modulo(quot * egcd.x, step);
+        ret = (modulo(r1 + m1 * k, modulus.clone()), modulus);
+    }
+    Some(ret)
+}
+
+/// Chinese remainder theorem on `(remainder, modulus)` pairs. `None` if they
+/// contradict each other or the combined modulus does not fit in an `i64`.
+pub fn crt(congruences: impl IntoIterator<Item = (i64, i64)>) -> Option<(i64, i64)> {
+    // Intermediate products of two i64 always fit in an i128
+    let fits = |m: &i128| *m <= i64::MAX as i128;
+    let congruences = congruences.into_iter().map(|(r, m)| (r as i128, m as i128));
+    let (r, m) = solve_crt(congruences, fits)?;
+    Some((r as i64, m as i64))
+}
+
+/// Like [`crt`], for when the combined modulus does not fit in an `i64`.
+pub fn big_crt(
+    congruences: impl IntoIterator<Item = (BigInt, BigInt)>,
+) -> Option<(BigInt, BigInt)> {
+    solve_crt(congruences, |_| true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gcd_lcm() {
+        assert_eq!(gcd([12, 18, 30]), 6);
+        assert_eq!(gcd::<u32>([]), 0);
+        assert_eq!(lcm([4u64, 6, 10]), 60);
+        assert_eq!(lcm([23, 19, 13, 17]), 96577);
+        assert_eq!(checked_lcm([1u64 << 40, 3 << 30]), Some(3 << 40));
+        assert_eq!(checked_lcm([1u64 << 60, 3, 5 << 30, 7]), None);
+        assert_eq!(
+            big_lcm([1u64 << 60, 3, 5 << 30, 7]),
+            BigUint::from(105u32) << 60
+        );
+    }
+
+    #[test]
+    fn modular() {
+        assert_eq!(modulo(-7, 5), 3);
+        assert_eq!(modulo(7, -5), 2);
+        assert_eq!(modulo(-7, -5), 3);
+        assert_eq!(mul_mod(u64::MAX, u64::MAX, 1_000_000_007), 114944269);
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(-3, 7), Some(2));
+        assert_eq!(mod_inverse(6, 9), None);
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(crt([(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt([(3, 4), (1, 6)]), Some((7, 12)));
+        assert_eq!(crt([(1, 4), (2, 6)]), None);
+        assert_eq!(crt([(0, i64::MAX), (1, 2)]), None);
+
+        let big = |(r, m): (i64, i64)| (BigInt::from(r), BigInt::from(m));
+        assert_eq!(
+            big_crt([(0, i64::MAX), (1, 2)].map(big)),
+            Some((BigInt::from(i64::MAX), BigInt::from(i64::MAX) * 2))
+        );
+    }
+}
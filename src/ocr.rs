@@ -0,0 +1,136 @@
+//! Reads the capital letters that puzzles draw with `#` and `.` pixels, 6
+//! pixels high and 4 wide, with a blank column after each letter.
+
+use std::fmt;
+
+/// Height of a letter, in pixels.
+pub const HEIGHT: usize = 6;
+
+/// Width of a letter and the blank column after it, in pixels.
+pub const CELL_WIDTH: usize = 5;
+
+/// The letters that show up in the puzzles.
+const FONT: [(char, [&str; HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    // `Y` is 5 pixels wide: its last column, lit in the top two rows, falls
+    // in the blank column and is not read.
+    ('Y', ["#...", "#...", ".#.#", "..#.", "..#.", "..#."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// A glyph that is not in the font.
+#[derive(Debug, PartialEq)]
+pub struct UnknownGlyph {
+    /// Position of the glyph in the text, from 0.
+    pub index: usize,
+    /// Rows of the glyph, in `#` and `.`.
+    pub rows: Vec<String>,
+}
+
+impl fmt::Display for UnknownGlyph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown glyph at position {}:\n{}",
+            self.index + 1,
+            self.rows.join("\n")
+        )
+    }
+}
+
+/// Reads the letters of a screen `width` pixels wide, given row by row. A
+/// glyph without any lit pixel reads as a space.
+pub fn read_letters(pixels: &[bool], width: usize) -> Result<String, UnknownGlyph> {
+    assert_eq!(
+        pixels.len(),
+        width * HEIGHT,
+        "The screen must be {HEIGHT} pixels high"
+    );
+
+    (0..width.div_ceil(CELL_WIDTH))
+        .map(|index| {
+            let cols = index * CELL_WIDTH..(index * CELL_WIDTH + CELL_WIDTH - 1).min(width);
+            let rows: Vec<String> = pixels
+                .chunks(width)
+                .map(|row| {
+                    row[cols.clone()]
+                        .iter()
+                        .map(|&lit| if lit { '#' } else { '.' })
+                        .collect()
+                })
+                .collect();
+
+            if rows.iter().all(|row| !row.contains('#')) {
+                return Ok(' ');
+            }
+            FONT.iter()
+                .find(|(_, glyph)| glyph[..] == rows[..])
+                .map(|&(letter, _)| letter)
+                .ok_or(UnknownGlyph { index, rows })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn screen(rows: &[&str]) -> (Vec<bool>, usize) {
+        let pixels = rows.iter().flat_map(|row| row.chars().map(|c| c == '#'));
+        (pixels.collect(), rows[0].len())
+    }
+
+    #[test]
+    fn letters() {
+        let (pixels, width) = screen(&[
+            "#..#.####.#....#.....##.......#..#.",
+            "#..#.#....#....#....#..#......#..#.",
+            "####.###..#....#....#..#......#..#.",
+            "#..#.#....#....#....#..#......#..#.",
+            "#..#.#....#....#....#..#......#..#.",
+            "#..#.####.####.####..##........##..",
+        ]);
+        assert_eq!(read_letters(&pixels, width), Ok("HELLO U".to_string()));
+
+        let (pixels, width) = screen(&[
+            "#..#.###", "#..#.#..", "####.#..", "#..#.#..", "#..#.#..", "#..#.###",
+        ]);
+        let error = read_letters(&pixels, width).unwrap_err();
+        assert_eq!(error.index, 1);
+        assert_eq!(
+            error.to_string(),
+            "Unknown glyph at position 2:\n###\n#..\n#..\n#..\n#..\n###"
+        );
+    }
+
+    #[test]
+    fn whole_font() {
+        let rows: Vec<String> = (0..HEIGHT)
+            .map(|y| {
+                FONT.iter()
+                    .map(|(_, glyph)| format!("{}.", glyph[y]))
+                    .collect()
+            })
+            .collect();
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        let (pixels, width) = screen(&rows);
+        let letters: String = FONT.iter().map(|&(letter, _)| letter).collect();
+        assert!(letters.contains('I') && letters.contains('Y'));
+        assert_eq!(read_letters(&pixels, width), Ok(letters));
+    }
+}
@@ -0,0 +1,51 @@
+//! Runs the parts of a solution and reports how long each one took. With the
+//! `alloc-stats` feature it also reports their peak heap usage and number of
+//! allocations.
+
+use std::time::Instant;
+
+#[cfg(feature = "alloc-stats")]
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Prints `name`, runs `part` and then prints its statistics, e.g.
+/// `[1.23ms]` or `[1.23ms | peak heap 4.0 KiB | 12 allocations]`.
+pub fn run_part<T>(name: &str, part: impl FnOnce() -> T) -> T {
+    println!("{name}:");
+
+    #[cfg(feature = "alloc-stats")]
+    let snapshot = crate::alloc::snapshot();
+    let start = Instant::now();
+
+    let ret = part();
+
+    let elapsed = start.elapsed();
+    #[cfg(feature = "alloc-stats")]
+    {
+        let stats = crate::alloc::since(&snapshot);
+        println!(
+            "[{:.2?} | peak heap {} | {} allocations]",
+            elapsed,
+            format_bytes(stats.peak),
+            stats.allocations
+        );
+    }
+    #[cfg(not(feature = "alloc-stats"))]
+    println!("[{:.2?}]", elapsed);
+
+    ret
+}
@@ -0,0 +1,285 @@
+//! Step-by-step debugger for the solutions that simulate something (the rope,
+//! the falling sand, the rocks, the walk on the cube and the elves). A day
+//! implements [`Simulation`] and hands it to [`debug`], which reads commands
+//! from stdin.
+
+use std::io::{BufRead, Write};
+
+/// Days whose solution can be run in the debugger.
+pub const DAYS: [u32; 5] = [9, 14, 17, 22, 23];
+
+pub trait Simulation {
+    /// Advances the simulation by one step. Returns `false`, without changing
+    /// anything, once the simulation has finished.
+    fn step(&mut self) -> bool;
+
+    /// Drawing of the current state.
+    fn render(&self) -> String;
+
+    /// Named values describing the current state. These are what `print`
+    /// shows and what `run until` conditions can refer to.
+    fn state(&self) -> Vec<(&'static str, i64)>;
+}
+
+const HELP: &str = "\
+Commands:
+    step [N]                  Advance N steps (default 1)
+    run                       Run until the simulation finishes
+    run until <name> <op> <value>
+                              Run until the condition holds, e.g. `run until height >= 100`.
+                              <op> is one of == != < <= > >=
+    show                      Draw the current state
+    print state               Print all state values
+    print <name>              Print a single state value
+    help                      Print this message
+    quit                      Exit the debugger";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, PartialEq)]
+struct Condition {
+    name: String,
+    op: Op,
+    value: i64,
+}
+
+impl Condition {
+    fn parse(words: &[&str]) -> Result<Self, String> {
+        let (name, op, value) = match words {
+            [name, op, value] => (name, op, value),
+            _ => return Err(String::from("Expected a condition like `height >= 100`")),
+        };
+        let op = match *op {
+            "==" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            op => return Err(format!("Unknown operator `{op}`")),
+        };
+        let value = value
+            .parse()
+            .map_err(|_| format!("`{value}` is not an integer"))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            op,
+            value,
+        })
+    }
+
+    fn holds(&self, state: &[(&str, i64)]) -> Result<bool, String> {
+        let lhs = lookup(state, &self.name)?;
+        Ok(match self.op {
+            Op::Eq => lhs == self.value,
+            Op::Ne => lhs != self.value,
+            Op::Lt => lhs < self.value,
+            Op::Le => lhs <= self.value,
+            Op::Gt => lhs > self.value,
+            Op::Ge => lhs >= self.value,
+        })
+    }
+}
+
+fn lookup(state: &[(&str, i64)], name: &str) -> Result<i64, String> {
+    match state.iter().find(|(key, _)| *key == name) {
+        Some(&(_, value)) => Ok(value),
+        None => Err(format!(
+            "Unknown state value `{name}`. Available: {}",
+            state
+                .iter()
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Wraps a simulation with the number of steps taken so far, which is
+/// available as the `step` state value.
+struct Debugger<S> {
+    sim: S,
+    steps: i64,
+    finished: bool,
+}
+
+impl<S: Simulation> Debugger<S> {
+    fn state(&self) -> Vec<(&'static str, i64)> {
+        let mut state = vec![("step", self.steps)];
+        state.extend(self.sim.state());
+        state
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished || !self.sim.step() {
+            self.finished = true;
+            return false;
+        }
+        self.steps += 1;
+        true
+    }
+
+    /// Steps at least once and until `stop` returns true or the simulation
+    /// finishes. Returns a line describing where it stopped.
+    fn run(
+        &mut self,
+        mut stop: impl FnMut(&Self) -> Result<bool, String>,
+    ) -> Result<String, String> {
+        loop {
+            if !self.step() {
+                return Ok(format!("finished after {} steps", self.steps));
+            }
+            if stop(self)? {
+                return Ok(format!("stopped at step {}", self.steps));
+            }
+        }
+    }
+
+    fn execute(&mut self, words: &[&str]) -> Result<String, String> {
+        match words {
+            ["step"] | ["s"] => self.execute(&["step", "1"]),
+            ["step", n] | ["s", n] => {
+                let n: usize = n
+                    .parse()
+                    .map_err(|_| format!("`{n}` is not a number of steps"))?;
+                if n == 0 {
+                    return Ok(format!("stopped at step {}", self.steps));
+                }
+                let mut taken = 0;
+                self.run(|_| {
+                    taken += 1;
+                    Ok(taken == n)
+                })
+            }
+            ["run"] => self.run(|_| Ok(false)),
+            ["run", "until", cond @ ..] => {
+                let cond = Condition::parse(cond)?;
+                // Check the condition is valid before running
+                cond.holds(&self.state())?;
+                self.run(|dbg| cond.holds(&dbg.state()))
+            }
+            ["show"] => Ok(self.sim.render().trim_end().to_string()),
+            ["print", "state"] | ["print"] => Ok(self
+                .state()
+                .iter()
+                .map(|(name, value)| format!("{name} = {value}"))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ["print", name] => lookup(&self.state(), name).map(|value| format!("{name} = {value}")),
+            ["help"] => Ok(HELP.to_string()),
+            _ => Err(format!(
+                "Unknown command `{}`. Type `help` for the list of commands",
+                words.join(" ")
+            )),
+        }
+    }
+}
+
+/// Reads commands from `input` until `quit` or end of input, writing the
+/// results to `output`.
+fn repl<S: Simulation>(sim: S, input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+    let mut dbg = Debugger {
+        sim,
+        steps: 0,
+        finished: false,
+    };
+
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] => (),
+            ["quit"] | ["q"] | ["exit"] => break,
+            _ => match dbg.execute(&words) {
+                Ok(msg) => writeln!(output, "{msg}")?,
+                Err(e) => writeln!(output, "error: {e}")?,
+            },
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    writeln!(output)
+}
+
+/// Runs the interactive debugger on `sim`, reading commands from stdin.
+pub fn debug(sim: impl Simulation) {
+    println!("Type `help` for the list of commands");
+    repl(sim, std::io::stdin().lock(), std::io::stdout()).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Counts up to 10
+    struct Counter(i64);
+
+    impl Simulation for Counter {
+        fn step(&mut self) -> bool {
+            if self.0 == 10 {
+                return false;
+            }
+            self.0 += 1;
+            true
+        }
+
+        fn render(&self) -> String {
+            "#".repeat(self.0 as usize)
+        }
+
+        fn state(&self) -> Vec<(&'static str, i64)> {
+            vec![("count", self.0)]
+        }
+    }
+
+    #[test]
+    fn debugger_commands() {
+        let input =
+            "step 3\nshow\nrun until count >= 7\nprint count\nbogus\nrun\nstep\nprint state\n";
+        let mut output = vec![];
+        repl(Counter(0), input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.split("> ").map(|ln| ln.trim_end()).collect();
+        assert_eq!(
+            lines,
+            [
+                "",
+                "stopped at step 3",
+                "###",
+                "stopped at step 7",
+                "count = 7",
+                "error: Unknown command `bogus`. Type `help` for the list of commands",
+                "finished after 10 steps",
+                "finished after 10 steps",
+                "step = 10\ncount = 10",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn condition_parsing() {
+        assert_eq!(
+            Condition::parse(&["height", ">=", "100"]),
+            Ok(Condition {
+                name: String::from("height"),
+                op: Op::Ge,
+                value: 100
+            })
+        );
+        assert!(Condition::parse(&["height", "=>", "100"]).is_err());
+        assert!(Condition::parse(&["height", ">="]).is_err());
+    }
+}
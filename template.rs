@@ -1,4 +1,4 @@
-use aoc::read_inputs;
+use aoc::{read_inputs, run_part};
 
 type InputT = Vec<String>;
 
@@ -6,6 +6,10 @@ fn part1(input: &InputT) {
     
 }
 
+fn part2(input: &InputT) {
+    
+}
+
 fn main() {
     let day: u32 = XX;
     #[cfg(debug_assertions)]
@@ -15,8 +19,6 @@ fn main() {
     let file_path = format!("data/{:02}.txt", day);
 
     let input: InputT = read_inputs(&file_path).unwrap();
-    println!("PART 1:");
-    part1(&input);
-    println!("PART 2:");
-    // part2(&input);
+    run_part("PART 1", || part1(&input));
+    run_part("PART 2", || part2(&input));
 }